@@ -2,6 +2,7 @@ use ctrait::{
     camera::Camera,
     entities, entity,
     entity::Entity,
+    error::CtraitResult,
     game::Game,
     graphics::{RenderContext, Renderer},
     math::Vector2,
@@ -29,32 +30,34 @@ impl Cursor {
 }
 
 impl Interactive for Cursor {
-    fn on_event(&mut self, event: &Event) {
+    fn on_event(&mut self, event: &Event) -> CtraitResult<()> {
         if let Event::MouseMotion { x, y, .. } = event {
             // Get cursor position relative to canvas.
             self.cursor_position = Vector2::new(*x, *y).cast();
         }
+        Ok(())
     }
 }
 
 impl Update for Cursor {
-    fn update(&mut self, _: f32) {
+    fn update(&mut self, _: f32) -> CtraitResult<()> {
         // The cursor position is relative to the canvas and not the world.
         // It must be converted first.
         let cursor_world_position = self
             .camera
-            .lock()
+            .read()
             .unwrap()
             .get_world_position(self.cursor_position);
         // Center the cursor rect to the mouse cursor's world position.
         self.rect
             .center_on(cursor_world_position.x, cursor_world_position.y);
+        Ok(())
     }
 }
 
 impl Renderable for Cursor {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.rect.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.rect.render(camera, context)
     }
 }
 
@@ -76,20 +79,21 @@ impl Detector {
 }
 
 impl Update for Detector {
-    fn update(&mut self, _: f32) {
-        self.colliding = self.cursor.lock().unwrap().rect.intersects(&self.rect);
+    fn update(&mut self, _: f32) -> CtraitResult<()> {
+        self.colliding = self.cursor.read().unwrap().rect.intersects(&self.rect);
         // Change the color of the rectangle depending on if it is colliding or not.
         self.rect.color = Some(if self.colliding {
             Color::GREEN
         } else {
             Color::WHITE
         });
+        Ok(())
     }
 }
 
 impl Renderable for Detector {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.rect.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.rect.render(camera, context)
     }
 }
 
@@ -1,11 +1,14 @@
 use ctrait::{
     camera::Camera,
-    entities, entity,
-    entity::{Entities, Entity},
+    command::Commands,
+    entity,
+    entity::Entity,
+    error::CtraitResult,
     game::Game,
     graphics::{RenderContext, Renderer},
     math::Vector2,
     rect::Rect,
+    spawnable,
     traits::{FixedUpdate, Interactive, Renderable, Update},
     Color, Event, Keycode,
 };
@@ -27,18 +30,21 @@ impl Block {
 }
 
 impl FixedUpdate for Block {
-    fn fixed_update(&mut self, delta: f32) {
+    fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
         // Increase the y position to make the block fall.
         self.rect.position.y += Self::FALL_SPEED * delta;
+        Ok(())
     }
 }
 
 impl Renderable for Block {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.rect.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.rect.render(camera, context)
     }
 }
 
+spawnable!(Block: FixedUpdate, Renderable);
+
 #[derive(Debug, Default)]
 struct Movement {
     left: bool,
@@ -48,8 +54,7 @@ struct Movement {
 struct Spawner {
     rect: Rect,
     movement: Movement,
-    renderable_entities: Entities<dyn Renderable>,
-    fixed_update_entities: Entities<dyn FixedUpdate>,
+    commands: Commands,
     // Vector owning all instantiated blocks. This makes it easy to manage the blocks after they
     // have been instantiated.
     blocks: Vec<Entity<Block>>,
@@ -58,31 +63,28 @@ struct Spawner {
 impl Spawner {
     const SPEED: f32 = 500.0;
 
-    fn new(
-        renderable_entities: Entities<dyn Renderable>,
-        fixed_update_entities: Entities<dyn FixedUpdate>,
-    ) -> Self {
+    fn new(commands: Commands) -> Self {
         Self {
             rect: Rect::from_center(0.0, -200.0, 100.0, 20.0).with_color(Color::GREEN),
             movement: Movement::default(),
-            renderable_entities,
-            fixed_update_entities,
+            commands,
             blocks: Vec::new(),
         }
     }
 }
 
 impl Update for Spawner {
-    fn update(&mut self, _: f32) {
+    fn update(&mut self, _: f32) -> CtraitResult<()> {
         // The internal implementation of entity container means that if an entity is dropped, its
         // references in the corresponding container(s) will also be removed.
         self.blocks
-            .retain(|block| block.lock().unwrap().rect.position.y < 100.0);
+            .retain(|block| block.read().unwrap().rect.position.y < 100.0);
+        Ok(())
     }
 }
 
 impl Interactive for Spawner {
-    fn on_event(&mut self, event: &Event) {
+    fn on_event(&mut self, event: &Event) -> CtraitResult<()> {
         match event {
             Event::KeyDown {
                 keycode: Some(keycode),
@@ -93,12 +95,10 @@ impl Interactive for Spawner {
                 } else if *keycode == Keycode::D {
                     self.movement.right = true;
                 } else if *keycode == Keycode::Space {
-                    // Instantiate a block.
+                    // Instantiate a block. The block is spawned through a command, which is
+                    // applied once it is safe to modify the containers.
                     let block = entity!(Block::new(self.rect.center()));
-                    self.renderable_entities
-                        .add_entities(&entities!(Renderable; block));
-                    self.fixed_update_entities
-                        .add_entities(&entities!(FixedUpdate; block));
+                    self.commands.spawn(&block);
                     // blocks is the new owner of the newly-instantiated block entity.
                     self.blocks.push(block);
                 }
@@ -115,44 +115,35 @@ impl Interactive for Spawner {
             }
             _ => {}
         }
+        Ok(())
     }
 }
 
 impl FixedUpdate for Spawner {
-    fn fixed_update(&mut self, delta: f32) {
+    fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
         if self.movement.left {
             self.rect.position.x -= Self::SPEED * delta;
         }
         if self.movement.right {
             self.rect.position.x += Self::SPEED * delta;
         }
+        Ok(())
     }
 }
 
 impl Renderable for Spawner {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.rect.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.rect.render(camera, context)
     }
 }
 
+spawnable!(Spawner: Update, Interactive, FixedUpdate, Renderable);
+
 fn main() {
     let mut renderer = Renderer::default().with_camera(Camera::default());
     let mut game = Game::new();
-    // References to entity containers are passed to spawner to allow it to instantiate entities
-    // during run-time.
-    let spawner = entity!(Spawner::new(
-        Entities::clone(&game.renderable_entities),
-        Entities::clone(&game.fixed_update_entities)
-    ));
-    game.update_entities
-        .add_entities(&entities!(Update; spawner));
-    game.interactive_entities
-        .add_entities(&entities!(Interactive; spawner));
-    game.fixed_update_entities
-        .add_entities(&entities!(FixedUpdate; spawner));
-    game.fixed_update_entities
-        .add_entities(&entities!(FixedUpdate; spawner));
-    game.renderable_entities
-        .add_entities(&entities!(Renderable; spawner));
+    // A command handle is passed to spawner to allow it to instantiate entities during run-time.
+    let spawner = entity!(Spawner::new(game.commands()));
+    game.spawn(&spawner);
     game.start(&mut renderer).unwrap();
 }
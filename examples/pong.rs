@@ -1,14 +1,18 @@
 use ctrait::{
     camera::Camera,
-    entities, entity,
+    deadlock, entity,
     entity::Entity,
+    error::CtraitResult,
     game::Game,
     graphics::{RenderContext, Renderer},
     math::Vector2,
     rect::Rect,
+    registry::Registry,
+    spawnable,
     traits::{FixedUpdate, Interactive, Renderable, Update},
     Color, Event, Keycode,
 };
+use std::time::Duration;
 
 #[derive(Debug, Default)]
 struct Movement {
@@ -38,18 +42,19 @@ impl Paddle {
 }
 
 impl FixedUpdate for Paddle {
-    fn fixed_update(&mut self, delta: f32) {
+    fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
         if self.movement.up {
             self.rect.position.y -= Self::SPEED * delta;
         }
         if self.movement.down {
             self.rect.position.y += Self::SPEED * delta;
         }
+        Ok(())
     }
 }
 
 impl Interactive for Paddle {
-    fn on_event(&mut self, event: &Event) {
+    fn on_event(&mut self, event: &Event) -> CtraitResult<()> {
         match event {
             Event::KeyDown {
                 keycode: Some(keycode),
@@ -73,12 +78,13 @@ impl Interactive for Paddle {
             }
             _ => {}
         };
+        Ok(())
     }
 }
 
 impl Renderable for Paddle {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.rect.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.rect.render(camera, context)
     }
 }
 
@@ -87,19 +93,17 @@ struct Ball {
     rect: Rect,
     velocity: Vector2<f32>,
     camera: Entity<Camera>,
-    paddle1: Entity<Paddle>,
-    paddle2: Entity<Paddle>,
+    registry: Registry,
 }
 
 impl Ball {
     const SPEED: f32 = 800.0;
-    fn new(camera: Entity<Camera>, paddle1: Entity<Paddle>, paddle2: Entity<Paddle>) -> Self {
+    fn new(camera: Entity<Camera>, registry: Registry) -> Self {
         Self {
             rect: Rect::from_center(0.0, 0.0, 10.0, 10.0).with_color(Color::WHITE),
             velocity: Vector2::new(-Self::SPEED, 0.0),
             camera,
-            paddle1,
-            paddle2,
+            registry,
         }
     }
 
@@ -116,10 +120,10 @@ impl Ball {
 }
 
 impl Update for Ball {
-    fn update(&mut self, _: f32) {
+    fn update(&mut self, _: f32) -> CtraitResult<()> {
         // Here, Update is implemented for Ball to check for collisions. Update is used rather than
         // FixedUpdate because none of the following code is time-dependent.
-        let camera = self.camera.lock().unwrap();
+        let camera = entity::read(&self.camera);
         let canvas_position = camera.get_canvas_position(self.rect.position);
         if canvas_position.x < 0.0 || canvas_position.x + self.rect.size.x >= camera.canvas_size().x
         {
@@ -132,53 +136,62 @@ impl Update for Ball {
             // The ball has reached the top or bottom bounds of the canvas. Invert its y velocity.
             self.velocity.y *= -1.0;
         } else {
-            // Check if the ball has collided with any of the two paddles.
-            let paddle1 = self.paddle1.lock().unwrap();
-            let paddle2 = self.paddle2.lock().unwrap();
-            if paddle1.rect.intersects(&self.rect) {
-                self.velocity.x = Self::SPEED;
-                self.velocity.y = Ball::calculate_y_velocity(&paddle1.movement);
-            } else if paddle2.rect.intersects(&self.rect) {
-                self.velocity.x = -Self::SPEED;
-                self.velocity.y = Ball::calculate_y_velocity(&paddle2.movement);
+            // Check if the ball has collided with any of the paddles, found through their tag.
+            // The paddles are locked with entity::read, so the deadlock diagnostics can track them.
+            for paddle in self.registry.with_tag::<Paddle>("paddle") {
+                let paddle = entity::read(&paddle);
+                if paddle.rect.intersects(&self.rect) {
+                    // Bounce the ball away from the paddle.
+                    self.velocity.x = if paddle.rect.position.x < self.rect.position.x {
+                        Self::SPEED
+                    } else {
+                        -Self::SPEED
+                    };
+                    self.velocity.y = Ball::calculate_y_velocity(&paddle.movement);
+                }
             }
         }
+        Ok(())
     }
 }
 
 impl FixedUpdate for Ball {
-    fn fixed_update(&mut self, delta: f32) {
+    fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
         self.rect.position += self.velocity * delta;
+        Ok(())
     }
 }
 
 impl Renderable for Ball {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.rect.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.rect.render(camera, context)
     }
 }
 
+// Register each type in the containers of the traits it implements.
+spawnable!(Paddle: FixedUpdate, Interactive, Renderable);
+spawnable!(Ball: Update, FixedUpdate, Renderable);
+
 fn main() {
+    // Report entities which cannot be locked within a second, rather than freezing the window.
+    if cfg!(debug_assertions) {
+        deadlock::enable(Duration::from_secs(1));
+    }
     // Define the camera as an entity so it can be referred to by Ball.
     let camera = entity!(Camera::default());
     let mut renderer = Renderer::default().with_camera_entity(Entity::clone(&camera));
     let paddle1 = entity!(Paddle::new(-400.0, Keycode::W, Keycode::S));
     let paddle2 = entity!(Paddle::new(400.0, Keycode::Up, Keycode::Down));
-    // The ball needs to know the positions of the paddles. Thus, references to the paddles are
-    // passed to the ball. Unlike the paddles, the camera is consumed because it is not referred to
-    // after this point.
-    let ball = entity!(Ball::new(
-        camera,
-        Entity::clone(&paddle1),
-        Entity::clone(&paddle2)
-    ));
     let mut game = Game::new();
-    game.update_entities.add_entities(&entities!(Update; ball));
-    game.fixed_update_entities
-        .add_entities(&entities!(FixedUpdate; paddle1, paddle2, ball));
-    game.renderable_entities
-        .add_entities(&entities!(Renderable; paddle1, paddle2, ball));
-    game.interactive_entities
-        .add_entities(&entities!(Interactive; paddle1, paddle2));
+    // The ball needs to know the positions of the paddles. Thus, the paddles are tagged so the
+    // ball can find them through the game's registry.
+    let registry = game.registry();
+    registry.add_tag(&paddle1, "paddle");
+    registry.add_tag(&paddle2, "paddle");
+    // Unlike the paddles, the camera is consumed because it is not referred to after this point.
+    let ball = entity!(Ball::new(camera, registry));
+    game.spawn(&paddle1);
+    game.spawn(&paddle2);
+    game.spawn(&ball);
     game.start(&mut renderer).unwrap();
 }
@@ -1,6 +1,7 @@
 use ctrait::{
     camera::Camera,
     entities, entity,
+    error::CtraitResult,
     game::Game,
     graphics::{RenderContext, Renderer},
     rect::Rect,
@@ -28,8 +29,8 @@ impl Image {
 }
 
 impl Renderable for Image {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.sprite.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.sprite.render(camera, context)
     }
 }
 
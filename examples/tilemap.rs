@@ -1,6 +1,7 @@
 use ctrait::{
     camera::Camera,
     entities, entity,
+    error::CtraitResult,
     game::Game,
     graphics::{RenderContext, Renderer},
     tile::{Tile, Tilemap, TilemapLayout},
@@ -39,8 +40,8 @@ impl World {
 }
 
 impl Renderable for World {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
-        self.tilemap.render(camera, context);
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+        self.tilemap.render(camera, context)
     }
 }
 
@@ -0,0 +1,327 @@
+//! Typed events exchanged between entities.
+
+use crate::{
+    entity::{self, Entity},
+    error::CtraitResult,
+    game::Isolation,
+};
+use std::{
+    any::{Any, TypeId},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+// Returns None once the subscriber is gone, such as when its entity was dropped.
+type Handler = Box<dyn FnMut(&dyn Any) -> Option<CtraitResult<()>> + Send>;
+
+struct Subscriber {
+    id: SubscriptionId,
+    event: TypeId,
+    // Type name reported with the subscriber's errors.
+    type_name: &'static str,
+    // Locked on its own, so the bus is not locked while the handler runs.
+    handler: Arc<Mutex<Handler>>,
+}
+
+#[derive(Default)]
+struct Bus {
+    next_id: u64,
+    queue: Vec<(TypeId, Box<dyn Any + Send>)>,
+    subscribers: Vec<Subscriber>,
+}
+
+/// Identifier of a subscription to an [`EventBus`], see [`EventBus::unsubscribe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Publish/subscribe bus of typed events, to notify entities without holding them.
+///
+/// Any `Send` type can be an event. Emitted events are queued and delivered once per frame,
+/// after the late updates (see the [phase order](crate::game::Game#phase-order)), to every
+/// subscriber of their type, in the order they were emitted. Events emitted while events are
+/// delivered, or later in the frame, are delivered in the next frame.
+///
+/// Errors returned by subscribers are reported like errors of entities, see
+/// [`Game::with_error_handler`](crate::game::Game::with_error_handler), and their panics are
+/// handled according to the game's [`PanicPolicy`](crate::game::PanicPolicy). The bus is
+/// unrelated to the SDL events delivered through [`Interactive`](crate::traits::Interactive).
+///
+/// The game owns a bus, see [`Game::event_bus`](crate::game::Game::event_bus). The handle can be
+/// cloned and stored in entities.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{entity, game::Game};
+///
+/// struct ScoreChanged(u32);
+///
+/// struct Scoreboard {
+///     score: u32,
+/// }
+///
+/// let mut game = Game::new();
+/// let scoreboard = entity!(Scoreboard { score: 0 });
+/// game.event_bus()
+///     .subscribe_entity(&scoreboard, |scoreboard, event: &ScoreChanged| {
+///         scoreboard.score = event.0;
+///         Ok(())
+///     });
+///
+/// game.emit(ScoreChanged(10));
+/// game.step(0.0);
+/// assert_eq!(scoreboard.read().unwrap().score, 10);
+/// ```
+#[derive(Clone, Default)]
+pub struct EventBus(Arc<Mutex<Bus>>);
+
+impl EventBus {
+    /// Queue the event, to be delivered to the subscribers of its type.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn emit<E: Send + 'static>(&self, event: E) {
+        self.0
+            .lock()
+            .unwrap()
+            .queue
+            .push((TypeId::of::<E>(), Box::new(event)));
+    }
+
+    /// Call the handler with every delivered event of type `E`.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn subscribe<E: 'static>(
+        &self,
+        mut handler: impl FnMut(&E) -> CtraitResult<()> + Send + 'static,
+    ) -> SubscriptionId {
+        self.add::<E>(
+            std::any::type_name_of_val(&handler),
+            Box::new(move |event| event.downcast_ref().map(&mut handler)),
+        )
+    }
+
+    /// Call the handler with the entity, locked for writing, and every delivered event of type
+    /// `E`.
+    ///
+    /// The entity is referred to weakly: once it is dropped, the subscription is removed.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn subscribe_entity<E: 'static, T: Send + Sync + 'static>(
+        &self,
+        entity: &Entity<T>,
+        mut handler: impl FnMut(&mut T, &E) -> CtraitResult<()> + Send + 'static,
+    ) -> SubscriptionId {
+        let weak = Arc::downgrade(entity);
+        self.add::<E>(
+            std::any::type_name::<T>(),
+            Box::new(move |event| {
+                let entity = weak.upgrade()?;
+                let event = event.downcast_ref()?;
+                let result = handler(&mut entity::write(&entity), event);
+                Some(result)
+            }),
+        )
+    }
+
+    /// Remove the subscription. Returns `false` if it was already removed.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut bus = self.0.lock().unwrap();
+        let len = bus.subscribers.len();
+        bus.subscribers.retain(|subscriber| subscriber.id != id);
+        bus.subscribers.len() != len
+    }
+
+    /// Returns the number of events waiting to be delivered.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.0.lock().unwrap().queue.len()
+    }
+
+    fn add<E: 'static>(&self, type_name: &'static str, handler: Handler) -> SubscriptionId {
+        let mut bus = self.0.lock().unwrap();
+        let id = SubscriptionId(bus.next_id);
+        bus.next_id += 1;
+        bus.subscribers.push(Subscriber {
+            id,
+            event: TypeId::of::<E>(),
+            type_name,
+            handler: Arc::new(Mutex::new(handler)),
+        });
+        id
+    }
+
+    // Deliver the queued events. Errors and panics of the subscribers are handled by the
+    // isolation, and subscribers which panicked are removed if its policy requests it.
+    pub(crate) fn dispatch(&self, isolation: &Isolation) {
+        let queue = std::mem::take(&mut self.0.lock().unwrap().queue);
+        let mut gone = Vec::new();
+        for (event_type, event) in queue {
+            // Handlers can use the bus, so it is not locked while they run.
+            let subscribers: Vec<_> = self
+                .0
+                .lock()
+                .unwrap()
+                .subscribers
+                .iter()
+                .filter(|subscriber| {
+                    subscriber.event == event_type && !gone.contains(&subscriber.id)
+                })
+                .map(|subscriber| {
+                    (
+                        subscriber.id,
+                        subscriber.type_name,
+                        Arc::clone(&subscriber.handler),
+                    )
+                })
+                .collect();
+            for (id, type_name, handler) in subscribers {
+                // The handler is poisoned if it panicked with PanicPolicy::Abort and the panic
+                // was caught further up.
+                let mut handler = handler.lock().unwrap_or_else(PoisonError::into_inner);
+                let mut dropped = false;
+                let keep = isolation.call_fn(type_name, "event_bus", || {
+                    handler(&*event).unwrap_or_else(|| {
+                        dropped = true;
+                        Ok(())
+                    })
+                });
+                if dropped || !keep {
+                    gone.push(id);
+                }
+            }
+        }
+        if !gone.is_empty() {
+            self.0
+                .lock()
+                .unwrap()
+                .subscribers
+                .retain(|subscriber| !gone.contains(&subscriber.id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventBus;
+    use crate::{
+        command::Commands,
+        error::CtraitError,
+        game::{Isolation, PanicPolicy},
+    };
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::{Arc, Mutex},
+    };
+
+    struct Ping(u32);
+    struct Pong;
+
+    fn isolation(policy: PanicPolicy) -> Isolation {
+        Isolation {
+            policy,
+            commands: Commands::default(),
+        }
+    }
+
+    #[test]
+    fn event_bus_dispatch() {
+        let bus = EventBus::default();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let clone = Arc::clone(&received);
+        let ping = bus.subscribe(move |event: &Ping| {
+            clone.lock().unwrap().push(event.0);
+            Ok(())
+        });
+        bus.subscribe(|_: &Pong| Err(CtraitError::Other(String::from("pong"))));
+        bus.emit(Ping(1));
+        bus.emit(Pong);
+        bus.emit(Ping(2));
+        assert_eq!(bus.pending(), 3);
+        let isolation = isolation(PanicPolicy::Skip);
+        bus.dispatch(&isolation);
+        assert_eq!(*received.lock().unwrap(), vec![1, 2]);
+        // The error is reported through a command.
+        assert_eq!(isolation.commands.take().len(), 1);
+        assert!(bus.unsubscribe(ping));
+        assert!(!bus.unsubscribe(ping));
+        bus.emit(Ping(3));
+        bus.dispatch(&isolation);
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn event_bus_emit_while_dispatching() {
+        let bus = EventBus::default();
+        let clone = EventBus::clone(&bus);
+        bus.subscribe(move |event: &Ping| {
+            clone.emit(Ping(event.0 + 1));
+            Ok(())
+        });
+        bus.emit(Ping(0));
+        bus.dispatch(&isolation(PanicPolicy::Skip));
+        // Events emitted by subscribers are delivered by the next dispatch.
+        assert_eq!(bus.pending(), 1);
+    }
+
+    #[test]
+    fn event_bus_entity() {
+        let bus = EventBus::default();
+        let entity = crate::entity!(0);
+        bus.subscribe_entity(&entity, |count: &mut u32, _: &Pong| {
+            *count += 1;
+            Ok(())
+        });
+        bus.emit(Pong);
+        bus.dispatch(&isolation(PanicPolicy::Skip));
+        assert_eq!(*entity.read().unwrap(), 1);
+        // Subscriptions of dropped entities are removed.
+        drop(entity);
+        bus.emit(Pong);
+        bus.dispatch(&isolation(PanicPolicy::Skip));
+        assert!(bus.0.lock().unwrap().subscribers.is_empty());
+    }
+
+    #[test]
+    fn event_bus_panic() {
+        let bus = EventBus::default();
+        let calls = Arc::new(Mutex::new(0));
+        let clone = Arc::clone(&calls);
+        bus.subscribe(move |_: &Ping| {
+            *clone.lock().unwrap() += 1;
+            panic!("subscriber failed");
+        });
+        // The subscriber is reported with the type of its handler rather than of its events.
+        let type_name = bus.0.lock().unwrap().subscribers[0].type_name;
+        assert!(type_name.contains("event_bus_panic::{{closure}}"));
+        // With PanicPolicy::Abort, the panic is resumed, but the subscriber stays usable.
+        bus.emit(Ping(0));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            bus.dispatch(&isolation(PanicPolicy::Abort));
+        }));
+        assert!(result.is_err());
+        // With PanicPolicy::Skip, the subscriber is kept.
+        bus.emit(Ping(1));
+        bus.dispatch(&isolation(PanicPolicy::Skip));
+        assert_eq!(*calls.lock().unwrap(), 2);
+        // With PanicPolicy::Remove, the subscriber is removed.
+        bus.emit(Ping(2));
+        bus.dispatch(&isolation(PanicPolicy::Remove));
+        bus.emit(Ping(3));
+        bus.dispatch(&isolation(PanicPolicy::Skip));
+        assert_eq!(*calls.lock().unwrap(), 3);
+        assert!(bus.0.lock().unwrap().subscribers.is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+//! Deferred commands applied by the game at safe points.
+
+use crate::{
+    entity::{Entities, Entity},
+    game::Game,
+    traits::Spawn,
+};
+use std::sync::{Arc, Mutex};
+
+type Command = Box<dyn FnOnce(&mut Game) + Send>;
+
+/// Handle to queue commands which are applied by a [`Game`] at a safe point.
+///
+/// Modifying entity containers from inside an entity's callback can deadlock, as the container
+/// might be locked while its entities are being called. Commands are instead queued and applied
+/// by the game at the end of each phase (see the [phase order](crate::game::Game#phase-order)),
+/// when no containers are being iterated.
+///
+/// The handle can be cloned and stored in entities.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{
+///     command::Commands,
+///     entities, entity,
+///     entity::{Entities, Entity},
+///     error::CtraitResult,
+///     game::Game,
+///     traits::Update,
+/// };
+///
+/// struct Bullet;
+/// impl Update for Bullet {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// struct Gun {
+///     commands: Commands,
+///     update_entities: Entities<dyn Update>,
+///     bullets: Vec<Entity<Bullet>>,
+/// }
+///
+/// impl Update for Gun {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         // Despawn the oldest bullet.
+///         if self.bullets.len() > 10 {
+///             self.commands.despawn(&self.bullets.remove(0));
+///         }
+///         let bullet = entity!(Bullet);
+///         // Adding the bullet to update_entities directly would deadlock, as the container is
+///         // being iterated while the gun is updated.
+///         self.commands
+///             .add_entities(&self.update_entities, &entities!(Update; bullet));
+///         self.bullets.push(bullet);
+///         Ok(())
+///     }
+/// }
+///
+/// let mut game = Game::new();
+/// let gun = entity!(Gun {
+///     commands: game.commands(),
+///     update_entities: Entities::clone(&game.update_entities),
+///     bullets: Vec::new(),
+/// });
+/// game.update_entities.add_entities(&entities!(Update; gun));
+/// game.step(0.0);
+/// assert_eq!(gun.read().unwrap().bullets.len(), 1);
+/// ```
+#[derive(Default, Clone)]
+pub struct Commands(Arc<Mutex<Vec<Command>>>);
+
+impl Commands {
+    /// Queue a command adding the given entities to the given container.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn add_entities<T: ?Sized + Send + Sync + 'static>(
+        &self,
+        container: &Entities<T>,
+        entities: &[Entity<T>],
+    ) {
+        let mut container = Entities::clone(container);
+        let entities = entities.to_vec();
+        self.push(move |_| container.add_entities(&entities));
+    }
+
+    /// Queue a command registering the given entity in every container of the game whose trait
+    /// it implements.
+    ///
+    /// See [`Game::spawn`].
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn spawn<T: Spawn>(&self, entity: &Entity<T>) {
+        let entity = Entity::clone(entity);
+        self.push(move |game| {
+            game.spawn(&entity);
+        });
+    }
+
+    /// Queue a command removing the given entity from every container of the game.
+    ///
+    /// See [`Game::despawn`].
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn despawn<T: ?Sized + Send + Sync + 'static>(&self, entity: &Entity<T>) {
+        let entity = Entity::clone(entity);
+        self.push(move |game| game.despawn(&entity));
+    }
+
+    /// Queue an arbitrary command.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn push<F: FnOnce(&mut Game) + Send + 'static>(&self, command: F) {
+        self.0.lock().unwrap().push(Box::new(command));
+    }
+
+    // Take all queued commands, leaving none behind.
+    pub(crate) fn take(&self) -> Vec<Command> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Commands, Game};
+
+    #[test]
+    fn commands_take() {
+        let commands = Commands::default();
+        Commands::clone(&commands).push(|game| game.set_paused(true));
+        let mut game = Game::default();
+        for command in commands.take() {
+            command(&mut game);
+        }
+        assert!(game.is_paused());
+        assert!(commands.take().is_empty());
+    }
+}
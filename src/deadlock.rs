@@ -0,0 +1,635 @@
+//! Diagnostics for deadlocks between entity locks.
+//!
+//! The game locks entities in every phase, and entities holding other entities lock them from
+//! their own trait methods. When two threads lock the same entities in different orders, such as
+//! [`Update::update`](crate::traits::Update::update) on the main thread and
+//! [`FixedUpdate::fixed_update`](crate::traits::FixedUpdate::fixed_update) on the fixed-update
+//! thread, they can deadlock, which freezes the game without any message.
+//!
+//! Once diagnostics are enabled with [`enable`], the entities locked by the game and with
+//! [`entity::read`](crate::entity::read) and [`entity::write`](crate::entity::write) are tracked:
+//!
+//! - Locks are attempted until a timeout. A lock which could not be acquired in time panics with a
+//!   report of the threads waiting on each other, with the types of the entities they hold and
+//!   the phases they were called in.
+//! - Every time an entity is locked while the thread holds other entities, the order is recorded.
+//!   Entities locked in an order forming a cycle are reported on the standard error as a
+//!   potential deadlock, even if the game did not deadlock yet.
+//!   The order of entities is forgotten once they are dropped, so an entity allocated at the
+//!   address of a dropped one does not inherit its order.
+//!
+//! Entities locked directly with the methods of [`RwLock`] are not tracked. Tracking is slower
+//! than locking, so it is meant for debug builds.
+//!
+//! # Examples
+//!
+//! ```
+//! use ctrait::deadlock;
+//! use std::time::Duration;
+//!
+//! if cfg!(debug_assertions) {
+//!     deadlock::enable(Duration::from_secs(2));
+//! }
+//! ```
+use crate::entity::{self, Entity};
+use std::{
+    cell::Cell,
+    collections::{btree_map, BTreeMap, BTreeSet},
+    fmt::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+static TRACKER: Tracker = Tracker::new();
+
+thread_local! {
+    // Phase of the entity call the thread is in, if any.
+    static PHASE: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Enable the diagnostics, panicking when an entity cannot be locked within the given timeout.
+///
+/// Enabling the diagnostics again only changes the timeout.
+pub fn enable(timeout: Duration) {
+    TRACKER.enable(timeout);
+}
+
+/// Disable the diagnostics. Entities locked while they were enabled are still tracked until
+/// they are unlocked.
+pub fn disable() {
+    TRACKER.enabled.store(false, Ordering::Relaxed);
+}
+
+/// Returns `true` if the diagnostics are enabled.
+#[must_use]
+pub fn is_enabled() -> bool {
+    TRACKER.enabled.load(Ordering::Relaxed)
+}
+
+// Returns `true` if the entity at `after` was locked while the same thread held `before`.
+#[cfg(test)]
+pub(crate) fn is_ordered(before: *const (), after: *const ()) -> bool {
+    TRACKER
+        .state()
+        .order
+        .get(&(before as usize))
+        .is_some_and(|node| node.after.contains_key(&(after as usize)))
+}
+
+// Set the phase of the current thread until the returned guard is dropped.
+pub(crate) fn enter(phase: &'static str) -> PhaseGuard {
+    PhaseGuard(PHASE.with(|current| current.replace(Some(phase))))
+}
+
+pub(crate) struct PhaseGuard(Option<&'static str>);
+
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        PHASE.with(|current| current.set(self.0));
+    }
+}
+
+// Lock the entity with lock, or with try_lock until the timeout if the diagnostics are enabled,
+// in which case the lock is tracked until the returned token is dropped.
+pub(crate) fn track<T: ?Sized + Send + Sync + 'static, G>(
+    entity: &Entity<T>,
+    access: Access,
+    lock: impl FnOnce() -> G,
+    try_lock: impl FnMut() -> Option<G>,
+) -> (G, Option<Held>) {
+    if is_enabled() {
+        let weak = Arc::downgrade(entity);
+        let (guard, id) = TRACKER.acquire(
+            entity::address(entity) as usize,
+            std::any::type_name::<T>(),
+            Arc::new(move || weak.strong_count() > 0),
+            access,
+            try_lock,
+        );
+        (guard, Some(Held(id)))
+    } else {
+        (lock(), None)
+    }
+}
+
+// A tracked lock, released from the tracker when dropped.
+pub(crate) struct Held(u64);
+
+impl Held {
+    // Replace the type name of the entity, known once it is locked.
+    pub(crate) fn set_type_name(&self, type_name: &'static str) {
+        let mut state = TRACKER.state();
+        if let Some(lock) = state.held.iter_mut().find(|lock| lock.id == self.0) {
+            lock.type_name = type_name;
+        }
+    }
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        TRACKER.release(self.0);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        })
+    }
+}
+
+// Returns `true` while the tracked entity is not dropped. It holds a weak reference to the
+// entity, which keeps its address from being reused by another entity.
+type Alive = Arc<dyn Fn() -> bool + Send + Sync>;
+
+// A lock held or waited for by a thread.
+struct Lock {
+    id: u64,
+    address: usize,
+    alive: Alive,
+    type_name: &'static str,
+    access: Access,
+    thread: ThreadId,
+    thread_name: Option<String>,
+    phase: Option<&'static str>,
+}
+
+impl Lock {
+    fn current(
+        id: u64,
+        address: usize,
+        alive: Alive,
+        type_name: &'static str,
+        access: Access,
+    ) -> Self {
+        let thread = thread::current();
+        Self {
+            id,
+            address,
+            alive,
+            type_name,
+            access,
+            thread: thread.id(),
+            thread_name: thread.name().map(String::from),
+            phase: PHASE.with(Cell::get),
+        }
+    }
+
+    // Thread and phase of the lock, such as "thread `main` in phase `update`".
+    fn context(&self) -> String {
+        let mut context = match &self.thread_name {
+            Some(name) => format!("thread `{}`", name),
+            None => format!("thread {:?}", self.thread),
+        };
+        if let Some(phase) = self.phase {
+            let _ = write!(context, " in phase `{}`", phase);
+        }
+        context
+    }
+}
+
+// An entity locked by a thread holding other entities, or held while locking other entities.
+struct Node {
+    alive: Alive,
+    // Addresses of the entities locked after this one by the same thread, with the description
+    // of the first occurrence.
+    after: BTreeMap<usize, String>,
+}
+
+// Minimum number of nodes before the nodes of dropped entities are removed.
+const MIN_PRUNE: usize = 64;
+
+struct State {
+    timeout: Duration,
+    next_id: u64,
+    held: Vec<Lock>,
+    waiting: Vec<Lock>,
+    // Order in which entities were locked, keyed by address.
+    order: BTreeMap<usize, Node>,
+    // Number of nodes left after the nodes of dropped entities were last removed.
+    pruned: usize,
+    // Edges already reported as part of a cycle.
+    reported: Vec<(usize, usize)>,
+}
+
+impl State {
+    // Type name of the entity with the given address, preferably as known by a holder.
+    fn type_name(&self, address: usize, fallback: &'static str) -> &'static str {
+        self.held
+            .iter()
+            .find(|lock| lock.address == address)
+            .map_or(fallback, |lock| lock.type_name)
+    }
+
+    // Node of the entity with the given address, replacing the node of a dropped entity whose
+    // address was reused.
+    fn node(&mut self, address: usize, alive: &Alive) -> &mut Node {
+        if self.order.get(&address).is_some_and(|node| !(node.alive)()) {
+            self.remove(&BTreeSet::from([address]));
+        }
+        if !self.order.contains_key(&address) && self.order.len() >= self.pruned.max(MIN_PRUNE) * 2
+        {
+            self.prune();
+        }
+        self.order.entry(address).or_insert_with(|| Node {
+            alive: Arc::clone(alive),
+            after: BTreeMap::new(),
+        })
+    }
+
+    // Remove the nodes of dropped entities, so the graph only grows with the live entities.
+    fn prune(&mut self) {
+        let dropped: BTreeSet<usize> = self
+            .order
+            .iter()
+            .filter(|(_, node)| !(node.alive)())
+            .map(|(&address, _)| address)
+            .collect();
+        self.remove(&dropped);
+        self.pruned = self.order.len();
+    }
+
+    // Remove the nodes with the given addresses and their edges.
+    fn remove(&mut self, addresses: &BTreeSet<usize>) {
+        self.order.retain(|address, _| !addresses.contains(address));
+        for node in self.order.values_mut() {
+            node.after.retain(|after, _| !addresses.contains(after));
+        }
+        self.reported
+            .retain(|(before, after)| !addresses.contains(before) && !addresses.contains(after));
+    }
+
+    // Record the order of the entities held by the thread of the lock before it, returning a
+    // report if it forms a cycle which was not reported yet.
+    fn record_order(&mut self, lock: &Lock) -> Option<String> {
+        let held: Vec<(usize, &'static str, Alive)> = self
+            .held
+            .iter()
+            .filter(|held| held.thread == lock.thread && held.address != lock.address)
+            .map(|held| (held.address, held.type_name, Arc::clone(&held.alive)))
+            .collect();
+        let mut report = None;
+        for (before, before_name, before_alive) in held {
+            self.node(lock.address, &lock.alive);
+            if self
+                .node(before, &before_alive)
+                .after
+                .contains_key(&lock.address)
+            {
+                continue;
+            }
+            let description = format!(
+                "`{}` then `{}` by {}",
+                before_name,
+                self.type_name(lock.address, lock.type_name),
+                lock.context()
+            );
+            if let Some(path) = self.path(lock.address, before) {
+                if !self.reported.contains(&(before, lock.address)) {
+                    let mut cycle = String::from("potential deadlock: entities locked in a cycle:");
+                    for (from, to) in path {
+                        let _ = write!(cycle, "\n  {}", self.order[&from].after[&to]);
+                    }
+                    let _ = write!(cycle, "\n  {}", description);
+                    self.reported.push((before, lock.address));
+                    report = Some(cycle);
+                }
+            }
+            if let Some(node) = self.order.get_mut(&before) {
+                node.after.insert(lock.address, description);
+            }
+        }
+        report
+    }
+
+    // Edges of a path from one address to another, if there is one.
+    fn path(&self, from: usize, to: usize) -> Option<Vec<(usize, usize)>> {
+        // Address each visited address was reached from.
+        let mut previous = BTreeMap::from([(from, from)]);
+        let mut stack = vec![from];
+        while let Some(address) = stack.pop() {
+            if address == to {
+                let mut path = Vec::new();
+                let mut current = to;
+                while current != from {
+                    let before = previous[&current];
+                    path.push((before, current));
+                    current = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let Some(node) = self.order.get(&address) else {
+                continue;
+            };
+            for &after in node.after.keys() {
+                if let btree_map::Entry::Vacant(entry) = previous.entry(after) {
+                    entry.insert(address);
+                    stack.push(after);
+                }
+            }
+        }
+        None
+    }
+
+    // Report of the waiting lock with the given identifier, following the chain of threads
+    // waiting on each other.
+    fn report(&self, id: u64) -> String {
+        let Some(mut current) = self.waiting.iter().find(|lock| lock.id == id) else {
+            return String::from("deadlock: lock not found");
+        };
+        let mut report = format!(
+            "deadlock: {} timed out waiting for a {} lock of entity `{}`",
+            current.context(),
+            current.access,
+            self.type_name(current.address, current.type_name)
+        );
+        let mut threads = vec![current.thread];
+        loop {
+            let access = current.access;
+            let Some(holder) = self.held.iter().find(|lock| {
+                lock.address == current.address
+                    && (access == Access::Write || lock.access == Access::Write)
+            }) else {
+                break;
+            };
+            let _ = write!(
+                report,
+                "\n  held with a {} lock by {}",
+                holder.access,
+                holder.context()
+            );
+            if threads.contains(&holder.thread) {
+                report.push_str(", closing the cycle");
+                break;
+            }
+            threads.push(holder.thread);
+            let Some(next) = self
+                .waiting
+                .iter()
+                .find(|lock| lock.thread == holder.thread)
+            else {
+                break;
+            };
+            let _ = write!(
+                report,
+                ", which waits for a {} lock of entity `{}`",
+                next.access,
+                self.type_name(next.address, next.type_name)
+            );
+            current = next;
+        }
+        report
+    }
+}
+
+pub(crate) struct Tracker {
+    enabled: AtomicBool,
+    state: Mutex<State>,
+}
+
+impl Tracker {
+    const fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            state: Mutex::new(State {
+                timeout: Duration::from_secs(1),
+                next_id: 0,
+                held: Vec::new(),
+                waiting: Vec::new(),
+                order: BTreeMap::new(),
+                pruned: 0,
+                reported: Vec::new(),
+            }),
+        }
+    }
+
+    fn enable(&self, timeout: Duration) {
+        self.state().timeout = timeout;
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Lock with try_lock until the timeout, returning the guard and the identifier of the lock.
+    fn acquire<G>(
+        &self,
+        address: usize,
+        type_name: &'static str,
+        alive: Alive,
+        access: Access,
+        mut try_lock: impl FnMut() -> Option<G>,
+    ) -> (G, u64) {
+        let (id, timeout) = {
+            let mut state = self.state();
+            let id = state.next_id;
+            state.next_id += 1;
+            let lock = Lock::current(id, address, alive, type_name, access);
+            if let Some(report) = state.record_order(&lock) {
+                eprintln!("{}", report);
+            }
+            state.waiting.push(lock);
+            (id, state.timeout)
+        };
+        let start = Instant::now();
+        let guard = loop {
+            if let Some(guard) = try_lock() {
+                break guard;
+            }
+            if start.elapsed() >= timeout {
+                let report = {
+                    let mut state = self.state();
+                    let report = state.report(id);
+                    state.waiting.retain(|lock| lock.id != id);
+                    report
+                };
+                panic!("{}", report);
+            }
+            thread::yield_now();
+        };
+        let mut state = self.state();
+        if let Some(position) = state.waiting.iter().position(|lock| lock.id == id) {
+            let lock = state.waiting.swap_remove(position);
+            state.held.push(lock);
+        }
+        (guard, id)
+    }
+
+    fn release(&self, id: u64) {
+        self.state().held.retain(|lock| lock.id != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{enter, Access, Alive, Tracker};
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Barrier, RwLock, TryLockError,
+        },
+        thread,
+        time::Duration,
+    };
+
+    fn try_write(lock: &RwLock<u32>) -> Option<std::sync::RwLockWriteGuard<'_, u32>> {
+        match lock.try_write() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    fn address(lock: &RwLock<u32>) -> usize {
+        (lock as *const RwLock<u32>) as usize
+    }
+
+    fn alive() -> Alive {
+        Arc::new(|| true)
+    }
+
+    #[test]
+    fn tracker_timeout() {
+        let tracker = Tracker::new();
+        tracker.enable(Duration::from_millis(50));
+        let (a, b) = (RwLock::new(0), RwLock::new(0));
+        let barrier = Barrier::new(2);
+        let report = thread::scope(|scope| {
+            let tracker = &tracker;
+            let (a, b, barrier) = (&a, &b, &barrier);
+            scope.spawn(move || {
+                let _phase = enter("fixed_update");
+                let (_guard, id) =
+                    tracker.acquire(address(b), "Paddle", alive(), Access::Write, || {
+                        try_write(b)
+                    });
+                barrier.wait();
+                // Held until the main thread times out.
+                thread::sleep(Duration::from_millis(200));
+                tracker.release(id);
+            });
+            let _phase = enter("update");
+            let (_guard, id) =
+                tracker.acquire(address(a), "Ball", alive(), Access::Write, || try_write(a));
+            barrier.wait();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                tracker.acquire(address(b), "Paddle", alive(), Access::Write, || {
+                    try_write(b)
+                })
+            }));
+            tracker.release(id);
+            result
+                .err()
+                .and_then(|payload| payload.downcast::<String>().ok())
+        });
+        let report = report.unwrap();
+        assert!(
+            report.contains("phase `update` timed out waiting for a write lock of entity `Paddle`")
+        );
+        assert!(report.contains("held with a write lock by thread"));
+        assert!(report.contains("in phase `fixed_update`"));
+        assert!(tracker.state().waiting.is_empty());
+        assert!(tracker.state().held.is_empty());
+    }
+
+    #[test]
+    fn tracker_order() {
+        let tracker = Tracker::new();
+        tracker.enable(Duration::from_secs(1));
+        let (a, b, c) = (RwLock::new(0), RwLock::new(0), RwLock::new(0));
+        let lock_in_order = |first: &RwLock<u32>, second: &RwLock<u32>| {
+            let (first_guard, first_id) =
+                tracker.acquire(address(first), "First", alive(), Access::Write, || {
+                    try_write(first)
+                });
+            let (second_guard, second_id) =
+                tracker.acquire(address(second), "Second", alive(), Access::Write, || {
+                    try_write(second)
+                });
+            drop((first_guard, second_guard));
+            tracker.release(second_id);
+            tracker.release(first_id);
+        };
+        lock_in_order(&a, &b);
+        lock_in_order(&b, &c);
+        assert!(tracker.state().reported.is_empty());
+        // c, a closes the cycle a, b, c.
+        lock_in_order(&c, &a);
+        assert_eq!(tracker.state().reported, vec![(address(&c), address(&a))]);
+        // Cycles are only reported once.
+        lock_in_order(&c, &a);
+        assert_eq!(tracker.state().reported.len(), 1);
+    }
+
+    #[test]
+    fn tracker_address_reuse() {
+        let tracker = Tracker::new();
+        tracker.enable(Duration::from_secs(1));
+        let (a, b) = (RwLock::new(0), RwLock::new(0));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let lock_in_order = |first: &RwLock<u32>, first_alive: Alive, second: &RwLock<u32>| {
+            let (first_guard, first_id) =
+                tracker.acquire(address(first), "First", first_alive, Access::Write, || {
+                    try_write(first)
+                });
+            let (second_guard, second_id) =
+                tracker.acquire(address(second), "Second", alive(), Access::Write, || {
+                    try_write(second)
+                });
+            drop((first_guard, second_guard));
+            tracker.release(second_id);
+            tracker.release(first_id);
+        };
+        let clone = Arc::clone(&dropped);
+        lock_in_order(&a, Arc::new(move || !clone.load(Ordering::Relaxed)), &b);
+        // The entity at the address of a is dropped, and the address is reused by another
+        // entity, locked after b: the edges of the dropped entity do not form a cycle.
+        dropped.store(true, Ordering::Relaxed);
+        lock_in_order(&b, alive(), &a);
+        assert!(tracker.state().reported.is_empty());
+        assert!(tracker.state().order[&address(&a)].after.is_empty());
+        assert!(tracker.state().order[&address(&b)]
+            .after
+            .contains_key(&address(&a)));
+    }
+
+    #[test]
+    fn tracker_prune() {
+        let tracker = Tracker::new();
+        tracker.enable(Duration::from_secs(1));
+        let (held, other) = (RwLock::new(0), RwLock::new(0));
+        let (_guard, id) = tracker.acquire(address(&held), "Held", alive(), Access::Write, || {
+            try_write(&held)
+        });
+        // Entities locked while held is held, then dropped, at addresses never reused.
+        for offset in 1..1000 {
+            let (guard, other_id) = tracker.acquire(
+                address(&held) + offset,
+                "Dropped",
+                Arc::new(|| false),
+                Access::Write,
+                || try_write(&other),
+            );
+            drop(guard);
+            tracker.release(other_id);
+        }
+        tracker.release(id);
+        // The nodes of dropped entities are removed once they outnumber the others.
+        assert!(tracker.state().order.len() <= super::MIN_PRUNE * 2);
+    }
+}
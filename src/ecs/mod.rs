@@ -0,0 +1,365 @@
+//! Component storage for large numbers of simple entities.
+//!
+//! Entities of the [`Game`](crate::game::Game)'s containers are trait objects behind a lock, so
+//! every access is a lock and a pointer chase. This scales poorly to tens of thousands of bullets
+//! or particles. For those, the game also holds a [`World`], storing plain components
+//! contiguously per type, and runs systems querying these components in the same phases as
+//! the trait containers (see [`Game::add_system`](crate::game::Game::add_system)).
+//!
+//! Both models can be used together: an entity of a trait container can own the identifiers of
+//! entities of the world, and systems can be registered alongside trait entities.
+//!
+//! # Examples
+//!
+//! ```
+//! use ctrait::{ecs::SystemPhase, game::Game};
+//!
+//! struct Position(f32, f32);
+//! struct Velocity(f32, f32);
+//!
+//! let mut game = Game::new();
+//! for i in 0..1000 {
+//!     game.world_mut()
+//!         .spawn((Position(0.0, 0.0), Velocity(i as f32, 0.0)));
+//! }
+//! game.add_system(SystemPhase::Update, |world, delta| {
+//!     world
+//!         .query::<(&mut Position, &Velocity)>()
+//!         .for_each(|(position, velocity)| {
+//!             position.0 += velocity.0 * delta;
+//!             position.1 += velocity.1 * delta;
+//!         });
+//!     Ok(())
+//! });
+//! game.step(1.0);
+//! ```
+mod query;
+mod storage;
+mod system;
+
+pub use query::{Query, QueryData};
+pub use storage::SparseSet;
+pub use system::SystemPhase;
+pub(crate) use system::Systems;
+
+use std::{
+    any::TypeId,
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+};
+use storage::Storage;
+
+/// Identifier of an entity of a [`World`], made of a slot index and a generation.
+///
+/// Once the entity is despawned, its slot may be reused with a greater generation, so an outdated
+/// identifier never refers to a different entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Id {
+    index: u32,
+    generation: u32,
+}
+
+impl Id {
+    fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    /// Index of the identifier's slot.
+    #[must_use]
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Generation of the identifier's slot, incremented every time the slot is reused.
+    #[must_use]
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+/// A set of components which can be spawned together with [`World::spawn`].
+///
+/// This is implemented for tuples of up to eight components. A component is any `Send` type
+/// with a `'static` lifetime.
+pub trait Bundle: Send + 'static {
+    /// Insert each component of the bundle for the given entity.
+    fn insert(self, world: &mut World, id: Id);
+}
+
+macro_rules! bundle {
+    ($($name:ident),+) => {
+        impl<$($name: Send + 'static),+> Bundle for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn insert(self, world: &mut World, id: Id) {
+                let ($($name,)+) = self;
+                $(world.insert(id, $name);)+
+            }
+        }
+    };
+}
+
+bundle!(A);
+bundle!(A, B);
+bundle!(A, B, C);
+bundle!(A, B, C, D);
+bundle!(A, B, C, D, E);
+bundle!(A, B, C, D, E, F);
+bundle!(A, B, C, D, E, F, G);
+bundle!(A, B, C, D, E, F, G, H);
+
+/// Storage of entities made of components, with one sparse set per component type.
+///
+/// Components are borrowed dynamically, which allows querying several component types at once
+/// through a shared reference. Borrowing a component type mutably while it is already borrowed
+/// panics, as with a [`RefCell`].
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::ecs::World;
+///
+/// struct Health(u32);
+/// struct Poisoned;
+///
+/// let mut world = World::new();
+/// let a = world.spawn((Health(10), Poisoned));
+/// let b = world.spawn((Health(10),));
+///
+/// world
+///     .query::<(&mut Health, &Poisoned)>()
+///     .for_each(|(health, _)| health.0 -= 1);
+/// assert_eq!(world.get::<Health>(a).unwrap().0, 9);
+/// assert_eq!(world.get::<Health>(b).unwrap().0, 10);
+///
+/// world.despawn(a);
+/// assert!(!world.is_alive(a));
+/// ```
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    len: usize,
+    storages: HashMap<TypeId, RefCell<Box<dyn Storage>>>,
+}
+
+impl World {
+    /// Constructs a new, empty world.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an entity with the given components and return its identifier.
+    ///
+    /// A single component is spawned as a one-element tuple, such as `world.spawn((Bullet,))`.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Id {
+        let index = if let Some(index) = self.free.pop() {
+            self.alive[index as usize] = true;
+            index
+        } else {
+            self.generations.push(0);
+            self.alive.push(true);
+            (self.generations.len() - 1) as u32
+        };
+        self.len += 1;
+        let id = Id::new(index, self.generations[index as usize]);
+        bundle.insert(self, id);
+        id
+    }
+
+    /// Remove the entity and all its components. Returns `true` if the entity was alive.
+    pub fn despawn(&mut self, id: Id) -> bool {
+        if !self.is_alive(id) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove(id);
+        }
+        let index = id.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(id.index);
+        self.len -= 1;
+        true
+    }
+
+    /// Returns `true` if the identifier refers to an entity which was not despawned.
+    #[must_use]
+    pub fn is_alive(&self, id: Id) -> bool {
+        let index = id.index as usize;
+        self.alive.get(index).copied().unwrap_or(false) && self.generations[index] == id.generation
+    }
+
+    /// Returns the number of entities in the world.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the world holds no entities.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert a component for the given entity, returning the component it replaces.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the entity was despawned.
+    pub fn insert<T: Send + 'static>(&mut self, id: Id, component: T) -> Option<T> {
+        assert!(self.is_alive(id), "entity {:?} was despawned", id);
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(SparseSet::<T>::default())))
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .and_then(|storage| storage.insert(id, component))
+    }
+
+    /// Remove and return the component of type `T` of the given entity, if it has one.
+    pub fn remove<T: Send + 'static>(&mut self, id: Id) -> Option<T> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()?
+            .take(id)
+    }
+
+    /// Returns the component of type `T` of the given entity, if it has one.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the components of type `T` are borrowed mutably.
+    #[must_use]
+    pub fn get<T: Send + 'static>(&self, id: Id) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.storage::<T>()?, |storage| storage.get(id)).ok()
+    }
+
+    /// Returns the component of type `T` of the given entity mutably, if it has one.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the components of type `T` are borrowed.
+    #[must_use]
+    pub fn get_mut<T: Send + 'static>(&self, id: Id) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.storage_mut::<T>()?, |storage| storage.get_mut(id)).ok()
+    }
+
+    /// Returns a query over the entities having every component requested by `Q`.
+    ///
+    /// `Q` is a tuple of `&T` and `&mut T`, where `T` are component types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::ecs::World;
+    ///
+    /// struct Position(f32);
+    /// struct Velocity(f32);
+    ///
+    /// let mut world = World::new();
+    /// world.spawn((Position(0.0), Velocity(2.0)));
+    /// world.spawn((Position(0.0),));
+    ///
+    /// let mut query = world.query::<(&mut Position, &Velocity)>();
+    /// query.for_each(|(position, velocity)| position.0 += velocity.0);
+    /// assert_eq!(query.count(), 1);
+    /// ```
+    #[must_use]
+    pub fn query<Q: QueryData>(&self) -> Query<'_, Q> {
+        Query::new(self)
+    }
+
+    fn storage<T: Send + 'static>(&self) -> Option<Ref<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow();
+        Some(Ref::map(storage, |storage| {
+            storage.as_any().downcast_ref::<SparseSet<T>>().unwrap()
+        }))
+    }
+
+    fn storage_mut<T: Send + 'static>(&self) -> Option<RefMut<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow_mut();
+        Some(RefMut::map(storage, |storage| {
+            storage.as_any_mut().downcast_mut::<SparseSet<T>>().unwrap()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Id, World};
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+
+    #[test]
+    fn world_spawn_despawn() {
+        let mut world = World::new();
+        let a = world.spawn((Position(0), Velocity(1)));
+        let b = world.spawn((Position(5),));
+        assert_eq!(world.len(), 2);
+        assert!(world.despawn(a));
+        assert!(!world.despawn(a));
+        assert!(world.get::<Position>(a).is_none());
+        // The slot of a is reused with a greater generation.
+        let c = world.spawn((Velocity(2),));
+        assert_eq!(c, Id::new(a.index(), a.generation() + 1));
+        assert!(world.get::<Position>(c).is_none());
+        assert_eq!(*world.get::<Position>(b).unwrap(), Position(5));
+        assert_eq!(world.len(), 2);
+    }
+
+    #[test]
+    fn world_insert_remove() {
+        let mut world = World::new();
+        let a = world.spawn((Position(0),));
+        assert_eq!(world.insert(a, Position(1)), Some(Position(0)));
+        assert_eq!(world.insert(a, Velocity(2)), None);
+        world.get_mut::<Velocity>(a).unwrap().0 += 1;
+        assert_eq!(world.remove::<Velocity>(a), Some(Velocity(3)));
+        assert_eq!(world.remove::<Velocity>(a), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "was despawned")]
+    fn world_insert_despawned() {
+        let mut world = World::new();
+        let a = world.spawn((Position(0),));
+        world.despawn(a);
+        world.insert(a, Velocity(0));
+    }
+
+    #[test]
+    fn world_query() {
+        let mut world = World::new();
+        let a = world.spawn((Position(0), Velocity(1)));
+        world.spawn((Position(0),));
+        let c = world.spawn((Velocity(3), Position(0)));
+        world
+            .query::<(&mut Position, &Velocity)>()
+            .for_each(|(position, velocity)| position.0 += velocity.0);
+        let mut positions = Vec::new();
+        world
+            .query::<(&Position,)>()
+            .for_each_with_id(|id, (position,)| positions.push((id, position.0)));
+        positions.sort();
+        assert_eq!(positions.len(), 3);
+        assert!(positions.contains(&(a, 1)));
+        assert!(positions.contains(&(c, 3)));
+        assert_eq!(world.query::<(&Velocity, &String)>().count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn world_query_aliasing() {
+        let mut world = World::new();
+        world.spawn((Position(0),));
+        world.query::<(&mut Position, &Position)>().for_each(|_| {});
+    }
+}
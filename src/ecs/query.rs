@@ -0,0 +1,228 @@
+use crate::ecs::{Id, SparseSet, World};
+use std::{
+    cell::{Ref, RefMut},
+    marker::PhantomData,
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Component types a [`Query`] can request.
+///
+/// This trait is sealed. It is implemented for `&T` and `&mut T`, where `T` is a component type,
+/// and for tuples of up to eight of these.
+///
+/// A query walks the storage with the fewest components, called the driving storage, in storage
+/// order: its components are accessed by position, while the components of the other storages
+/// are looked up by identifier.
+pub trait QueryData: sealed::Sealed {
+    /// Borrowed component storages, held while the query runs.
+    type Guard<'w>;
+
+    /// Item yielded for every matching entity.
+    type Item<'a>;
+
+    /// Borrow the storages of the world, or return [`None`] if a storage does not exist, in which
+    /// case no entity matches.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a storage is already borrowed incompatibly, such as when a
+    /// component type is requested more than once.
+    fn borrow(world: &World) -> Option<Self::Guard<'_>>;
+
+    /// Number of components of the driving storage, among which every matching entity is.
+    fn len(guard: &Self::Guard<'_>) -> usize;
+
+    /// Returns the identifier of the entity at the given position of the driving storage.
+    fn id_at(guard: &Self::Guard<'_>, position: usize) -> Id;
+
+    /// Returns the item of the entity at the given position of the driving storage, if it
+    /// matches.
+    fn fetch_at<'a>(guard: &'a mut Self::Guard<'_>, position: usize) -> Option<Self::Item<'a>>;
+
+    /// Returns the item of the given entity, if it matches.
+    fn fetch<'a>(guard: &'a mut Self::Guard<'_>, id: Id) -> Option<Self::Item<'a>>;
+}
+
+impl<T: Send + 'static> sealed::Sealed for &T {}
+
+impl<T: Send + 'static> QueryData for &T {
+    type Guard<'w> = Ref<'w, SparseSet<T>>;
+    type Item<'a> = &'a T;
+
+    fn borrow(world: &World) -> Option<Self::Guard<'_>> {
+        world.storage::<T>()
+    }
+
+    fn len(guard: &Self::Guard<'_>) -> usize {
+        guard.ids().len()
+    }
+
+    fn id_at(guard: &Self::Guard<'_>, position: usize) -> Id {
+        guard.ids()[position]
+    }
+
+    fn fetch_at<'a>(guard: &'a mut Self::Guard<'_>, position: usize) -> Option<Self::Item<'a>> {
+        guard.components().get(position)
+    }
+
+    fn fetch<'a>(guard: &'a mut Self::Guard<'_>, id: Id) -> Option<Self::Item<'a>> {
+        guard.get(id)
+    }
+}
+
+impl<T: Send + 'static> sealed::Sealed for &mut T {}
+
+impl<T: Send + 'static> QueryData for &mut T {
+    type Guard<'w> = RefMut<'w, SparseSet<T>>;
+    type Item<'a> = &'a mut T;
+
+    fn borrow(world: &World) -> Option<Self::Guard<'_>> {
+        world.storage_mut::<T>()
+    }
+
+    fn len(guard: &Self::Guard<'_>) -> usize {
+        guard.ids().len()
+    }
+
+    fn id_at(guard: &Self::Guard<'_>, position: usize) -> Id {
+        guard.ids()[position]
+    }
+
+    fn fetch_at<'a>(guard: &'a mut Self::Guard<'_>, position: usize) -> Option<Self::Item<'a>> {
+        guard.components_mut().get_mut(position)
+    }
+
+    fn fetch<'a>(guard: &'a mut Self::Guard<'_>, id: Id) -> Option<Self::Item<'a>> {
+        guard.get_mut(id)
+    }
+}
+
+macro_rules! query_data {
+    ($($name:ident: $index:tt),+) => {
+        impl<$($name: QueryData),+> sealed::Sealed for ($($name,)+) {}
+
+        impl<$($name: QueryData),+> QueryData for ($($name,)+) {
+            // The storages, and the index of the driving one, chosen once borrowed.
+            type Guard<'w> = (($($name::Guard<'w>,)+), usize);
+            type Item<'a> = ($($name::Item<'a>,)+);
+
+            fn borrow(world: &World) -> Option<Self::Guard<'_>> {
+                let guards = ($($name::borrow(world)?,)+);
+                // Drive the query with the smallest storage.
+                let mut driver = (0, usize::MAX);
+                $(
+                    let len = $name::len(&guards.$index);
+                    if len < driver.1 {
+                        driver = ($index, len);
+                    }
+                )+
+                Some((guards, driver.0))
+            }
+
+            fn len((guards, driver): &Self::Guard<'_>) -> usize {
+                $(
+                    if *driver == $index {
+                        return $name::len(&guards.$index);
+                    }
+                )+
+                0
+            }
+
+            fn id_at((guards, driver): &Self::Guard<'_>, position: usize) -> Id {
+                $(
+                    if *driver == $index {
+                        return $name::id_at(&guards.$index, position);
+                    }
+                )+
+                unreachable!("the driving storage is one of the storages of the query")
+            }
+
+            fn fetch_at<'a>(
+                guard: &'a mut Self::Guard<'_>,
+                position: usize,
+            ) -> Option<Self::Item<'a>> {
+                let id = Self::id_at(guard, position);
+                let (guards, driver) = guard;
+                Some(($(
+                    if *driver == $index {
+                        $name::fetch_at(&mut guards.$index, position)?
+                    } else {
+                        $name::fetch(&mut guards.$index, id)?
+                    },
+                )+))
+            }
+
+            fn fetch<'a>((guards, _): &'a mut Self::Guard<'_>, id: Id) -> Option<Self::Item<'a>> {
+                Some(($($name::fetch(&mut guards.$index, id)?,)+))
+            }
+        }
+    };
+}
+
+query_data!(A: 0);
+query_data!(A: 0, B: 1);
+query_data!(A: 0, B: 1, C: 2);
+query_data!(A: 0, B: 1, C: 2, D: 3);
+query_data!(A: 0, B: 1, C: 2, D: 3, E: 4);
+query_data!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+query_data!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+query_data!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Query over the entities of a [`World`] having every component requested by `Q`.
+///
+/// See [`World::query`].
+pub struct Query<'w, Q: QueryData> {
+    world: &'w World,
+    data: PhantomData<Q>,
+}
+
+impl<'w, Q: QueryData> Query<'w, Q> {
+    pub(crate) fn new(world: &'w World) -> Self {
+        Self {
+            world,
+            data: PhantomData,
+        }
+    }
+
+    /// Call the given function with the components of every matching entity.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a component type is requested more than once, or if a requested
+    /// storage is borrowed incompatibly by another query running at the same time.
+    pub fn for_each<F: FnMut(Q::Item<'_>)>(&mut self, mut f: F) {
+        self.for_each_with_id(|_, item| f(item));
+    }
+
+    /// Call the given function with the identifier and the components of every matching entity.
+    ///
+    /// # Panics
+    ///
+    /// See [`Self::for_each`].
+    pub fn for_each_with_id<F: FnMut(Id, Q::Item<'_>)>(&mut self, mut f: F) {
+        if let Some(mut guard) = Q::borrow(self.world) {
+            // The storages are borrowed by the query, so the driving storage keeps its length.
+            for position in 0..Q::len(&guard) {
+                let id = Q::id_at(&guard, position);
+                if let Some(item) = Q::fetch_at(&mut guard, position) {
+                    f(id, item);
+                }
+            }
+        }
+    }
+
+    /// Returns the number of matching entities.
+    ///
+    /// # Panics
+    ///
+    /// See [`Self::for_each`].
+    #[must_use]
+    pub fn count(&mut self) -> usize {
+        let mut count = 0;
+        self.for_each_with_id(|_, _| count += 1);
+        count
+    }
+}
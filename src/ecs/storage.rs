@@ -0,0 +1,135 @@
+use crate::ecs::Id;
+use std::any::Any;
+
+// Type-erased component storage, so the world can store storages of every component type.
+pub(crate) trait Storage: Send {
+    // Remove the component of the given entity, if any.
+    fn remove(&mut self, id: Id);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Sparse set of components of a single type.
+///
+/// Components are stored contiguously, which makes iterating them cache friendly. The sparse
+/// array maps the index of an entity to the position of its component. Sets are only accessed
+/// through [`Query`](crate::ecs::Query), which borrows them as its
+/// [`QueryData::Guard`](crate::ecs::QueryData::Guard).
+pub struct SparseSet<T> {
+    sparse: Vec<Option<usize>>,
+    ids: Vec<Id>,
+    components: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            ids: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    // Position of the component of the given entity.
+    fn position(&self, id: Id) -> Option<usize> {
+        let position = (*self.sparse.get(id.index as usize)?)?;
+        // The slot might be occupied by an entity of another generation.
+        (self.ids[position] == id).then_some(position)
+    }
+
+    // Insert the component of the given entity, returning the previous one.
+    pub(crate) fn insert(&mut self, id: Id, component: T) -> Option<T> {
+        if let Some(position) = self.position(id) {
+            return Some(std::mem::replace(&mut self.components[position], component));
+        }
+        let index = id.index as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.ids.len());
+        self.ids.push(id);
+        self.components.push(component);
+        None
+    }
+
+    pub(crate) fn take(&mut self, id: Id) -> Option<T> {
+        let position = self.position(id)?;
+        self.sparse[id.index as usize] = None;
+        self.ids.swap_remove(position);
+        let component = self.components.swap_remove(position);
+        // The last component was moved to the position of the removed one.
+        if let Some(moved) = self.ids.get(position) {
+            self.sparse[moved.index as usize] = Some(position);
+        }
+        Some(component)
+    }
+
+    pub(crate) fn get(&self, id: Id) -> Option<&T> {
+        self.position(id).map(|position| &self.components[position])
+    }
+
+    pub(crate) fn get_mut(&mut self, id: Id) -> Option<&mut T> {
+        self.position(id)
+            .map(move |position| &mut self.components[position])
+    }
+
+    // Entities having a component, in storage order.
+    pub(crate) fn ids(&self) -> &[Id] {
+        &self.ids
+    }
+
+    // Components, in storage order.
+    pub(crate) fn components(&self) -> &[T] {
+        &self.components
+    }
+
+    pub(crate) fn components_mut(&mut self) -> &mut [T] {
+        &mut self.components
+    }
+}
+
+impl<T: Send + 'static> Storage for SparseSet<T> {
+    fn remove(&mut self, id: Id) {
+        self.take(id);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Id, SparseSet};
+
+    #[test]
+    fn sparse_set_insert_take() {
+        let a = Id::new(0, 0);
+        let b = Id::new(3, 0);
+        let mut set = SparseSet::default();
+        assert_eq!(set.insert(a, 'a'), None);
+        assert_eq!(set.insert(b, 'b'), None);
+        assert_eq!(set.insert(a, 'c'), Some('a'));
+        assert_eq!(set.take(a), Some('c'));
+        assert_eq!(set.take(a), None);
+        // b was moved into the position of a.
+        assert_eq!(set.get(b), Some(&'b'));
+        assert_eq!(set.ids(), [b]);
+    }
+
+    #[test]
+    fn sparse_set_generation() {
+        let mut set = SparseSet::default();
+        set.insert(Id::new(0, 0), 1);
+        assert_eq!(set.get(Id::new(0, 1)), None);
+        assert_eq!(set.get_mut(Id::new(0, 0)), Some(&mut 1));
+    }
+}
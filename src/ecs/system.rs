@@ -0,0 +1,77 @@
+use crate::{ecs::World, error::CtraitResult, game::Isolation};
+
+type SystemFn = Box<dyn FnMut(&mut World, f32) -> CtraitResult<()> + Send>;
+
+/// Phase of the game loop a system is run in.
+///
+/// Systems of a phase are run after the entities of the corresponding trait, see the
+/// [phase order](crate::game::Game#phase-order). They receive the scaled `delta` of the phase and
+/// are not run while the game is paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemPhase {
+    /// Run with [`PreUpdate`](crate::traits::PreUpdate) entities.
+    PreUpdate,
+    /// Run with [`FixedUpdate`](crate::traits::FixedUpdate) entities, once per fixed step.
+    ///
+    /// With [`FixedUpdateMode::Threaded`](crate::game::FixedUpdateMode::Threaded), the systems are
+    /// still stepped on the thread running the game loop, as the world is not shared with the
+    /// timer thread.
+    FixedUpdate,
+    /// Run with [`Update`](crate::traits::Update) entities.
+    Update,
+    /// Run with [`LateUpdate`](crate::traits::LateUpdate) entities.
+    LateUpdate,
+}
+
+impl SystemPhase {
+    // Name of the phase in error reports, matching the name of the trait method.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::PreUpdate => "pre_update",
+            Self::FixedUpdate => "fixed_update",
+            Self::Update => "update",
+            Self::LateUpdate => "late_update",
+        }
+    }
+}
+
+// A registered system.
+struct System {
+    phase: SystemPhase,
+    type_name: &'static str,
+    run: SystemFn,
+}
+
+// Systems of a game, run in the order they were added.
+#[derive(Default)]
+pub(crate) struct Systems(Vec<System>);
+
+impl Systems {
+    pub(crate) fn push<F>(&mut self, phase: SystemPhase, system: F)
+    where
+        F: FnMut(&mut World, f32) -> CtraitResult<()> + Send + 'static,
+    {
+        self.0.push(System {
+            phase,
+            type_name: std::any::type_name::<F>(),
+            run: Box::new(system),
+        });
+    }
+
+    // Run the systems of the given phase. Errors and panics of the systems are handled by the
+    // isolation, and systems which panicked are removed if its policy requests it.
+    pub(crate) fn run(
+        &mut self,
+        phase: SystemPhase,
+        world: &mut World,
+        delta: f32,
+        isolation: &Isolation,
+    ) {
+        self.0.retain_mut(|system| {
+            system.phase != phase
+                || isolation.call_fn(system.type_name, phase.name(), || {
+                    (system.run)(world, delta)
+                })
+        });
+    }
+}
@@ -1,10 +1,135 @@
 //! Entity type and container.
-use std::sync::{Arc, Mutex, Weak};
+use crate::deadlock::{self, Access, Held};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, Weak,
+    },
+};
 
 /// A type representing a single game entity.
-pub type Entity<T> = Arc<Mutex<T>>;
+///
+/// Entities are behind a reader/writer lock: phases which only read entities, such as rendering,
+/// share access to them with [`RwLock::read`], while phases which mutate them, such as updates,
+/// take exclusive access with [`RwLock::write`]. As entities are shared between threads, their
+/// types must be [`Sync`] as well as [`Send`].
+pub type Entity<T> = Arc<RwLock<T>>;
+
+type WeakEntity<T> = Weak<RwLock<T>>;
+
+// Address of the entity's allocation, which identifies it regardless of its trait object type.
+pub(crate) fn address<T: ?Sized>(entity: &Entity<T>) -> *const () {
+    Arc::as_ptr(entity).cast()
+}
+
+/// Lock the entity for reading, recovering it if a previous user panicked while writing.
+///
+/// Unlike [`RwLock::read`], the lock is tracked by the [`deadlock`] diagnostics when they are
+/// enabled, which makes this function preferable when an entity locks other entities.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{entity, entity::Entity};
+///
+/// struct Paddle {
+///     y: f32,
+/// }
+///
+/// struct Ball {
+///     paddle: Entity<Paddle>,
+/// }
+///
+/// let ball = entity!(Ball {
+///     paddle: entity!(Paddle { y: 4.0 }),
+/// });
+/// let y = entity::read(&entity::read(&ball).paddle).y;
+/// assert_eq!(y, 4.0);
+/// ```
+pub fn read<T: ?Sized + Send + Sync + 'static>(entity: &Entity<T>) -> ReadGuard<'_, T> {
+    let (guard, held) = deadlock::track(
+        entity,
+        Access::Read,
+        || entity.read().unwrap_or_else(PoisonError::into_inner),
+        || match entity.try_read() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        },
+    );
+    ReadGuard { guard, held }
+}
+
+/// Lock the entity for writing, recovering it if a previous user panicked while writing.
+///
+/// See [`read`].
+pub fn write<T: ?Sized + Send + Sync + 'static>(entity: &Entity<T>) -> WriteGuard<'_, T> {
+    let (guard, held) = deadlock::track(
+        entity,
+        Access::Write,
+        || entity.write().unwrap_or_else(PoisonError::into_inner),
+        || match entity.try_write() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        },
+    );
+    WriteGuard { guard, held }
+}
+
+/// Shared access to an entity, returned by [`read`].
+pub struct ReadGuard<'a, T: ?Sized> {
+    guard: RwLockReadGuard<'a, T>,
+    // Dropped after the guard, once the entity is unlocked.
+    held: Option<Held>,
+}
+
+impl<T: ?Sized> ReadGuard<'_, T> {
+    pub(crate) fn set_type_name(&self, type_name: &'static str) {
+        if let Some(held) = &self.held {
+            held.set_type_name(type_name);
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+/// Exclusive access to an entity, returned by [`write`].
+pub struct WriteGuard<'a, T: ?Sized> {
+    guard: RwLockWriteGuard<'a, T>,
+    // Dropped after the guard, once the entity is unlocked.
+    held: Option<Held>,
+}
+
+impl<T: ?Sized> WriteGuard<'_, T> {
+    pub(crate) fn set_type_name(&self, type_name: &'static str) {
+        if let Some(held) = &self.held {
+            held.set_type_name(type_name);
+        }
+    }
+}
 
-type WeakEntity<T> = Weak<Mutex<T>>;
+impl<T: ?Sized> Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
 
 /// Macro to quickly create a new entity.
 ///
@@ -21,7 +146,7 @@ type WeakEntity<T> = Weak<Mutex<T>>;
 #[macro_export]
 macro_rules! entity {
     ($object:expr) => {
-        std::sync::Arc::new(std::sync::Mutex::new($object))
+        std::sync::Arc::new(std::sync::RwLock::new($object))
     };
 }
 
@@ -57,11 +182,24 @@ macro_rules! entities {
     };
 }
 
+// Called with every entity added to a container.
+type OnAdd<T> = Arc<dyn Fn(&Entity<T>) + Send + Sync>;
+
 /// Entity container holding a [`Vec`] of [`Weak`] references to entities.
 ///
+/// The containers of [`Game`](crate::game::Game) assign an [`EntityId`] to the entities added to
+/// them, see [`Game::entity_ids`](crate::game::Game::entity_ids).
+///
 /// This structure is thread-safe.
-#[derive(Debug)]
-pub struct Entities<T: ?Sized>(Arc<Mutex<Vec<WeakEntity<T>>>>);
+pub struct Entities<T: ?Sized> {
+    entities: Arc<Mutex<Vec<WeakEntity<T>>>>,
+    // Addresses of the entities added to the container, including some which were removed since,
+    // so that entities not in the container are told apart without scanning it.
+    addresses: Arc<Mutex<HashSet<usize>>>,
+    on_add: Option<OnAdd<T>>,
+    // Whether the entities must be updated on the thread running the game loop.
+    main_thread: Arc<AtomicBool>,
+}
 
 impl<T: ?Sized> Default for Entities<T> {
     fn default() -> Self {
@@ -71,7 +209,21 @@ impl<T: ?Sized> Default for Entities<T> {
 
 impl<T: ?Sized> Clone for Entities<T> {
     fn clone(&self) -> Self {
-        Self(Arc::clone(&self.0))
+        Self {
+            entities: Arc::clone(&self.entities),
+            addresses: Arc::clone(&self.addresses),
+            on_add: self.on_add.clone(),
+            main_thread: Arc::clone(&self.main_thread),
+        }
+    }
+}
+
+impl<T: ?Sized> std::fmt::Debug for Entities<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entities")
+            .field("entities", &self.entities)
+            .field("main_thread", &self.main_thread)
+            .finish_non_exhaustive()
     }
 }
 
@@ -87,7 +239,26 @@ impl<T: ?Sized> Entities<T> {
     /// ```
     #[must_use]
     pub fn new() -> Self {
-        Self(Arc::new(Mutex::new(Vec::new())))
+        Self {
+            entities: Arc::default(),
+            addresses: Arc::default(),
+            on_add: None,
+            main_thread: Arc::default(),
+        }
+    }
+
+    // Constructs a new entity container assigning an identifier to every entity added to it.
+    pub(crate) fn with_ids(ids: &EntityIds) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        let ids = EntityIds::clone(ids);
+        Self {
+            on_add: Some(Arc::new(move |entity| {
+                ids.id(entity);
+            })),
+            ..Self::new()
+        }
     }
 
     /// Add entities from a given entity slice.
@@ -100,6 +271,7 @@ impl<T: ?Sized> Entities<T> {
     /// use ctrait::{
     ///     camera::Camera,
     ///     entity, entities,
+    ///     error::CtraitResult,
     ///     game::Game,
     ///     graphics::RenderContext,
     ///     traits::Renderable
@@ -107,11 +279,15 @@ impl<T: ?Sized> Entities<T> {
     ///
     /// struct A;
     /// impl Renderable for A {
-    ///     fn render(&self, _: &Camera, _: &mut RenderContext) {}
+    ///     fn render(&self, _: &Camera, _: &mut RenderContext) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
     /// }
     /// struct B;
     /// impl Renderable for B {
-    ///     fn render(&self, _: &Camera, _: &mut RenderContext) {}
+    ///     fn render(&self, _: &Camera, _: &mut RenderContext) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
     /// }
     ///
     /// let a = entity!(A {});
@@ -133,12 +309,205 @@ impl<T: ?Sized> Entities<T> {
     ///
     /// This function might panic if another user of the container panics.
     pub fn clear(&mut self) {
-        self.0.lock().unwrap().clear();
+        self.addresses.lock().unwrap().clear();
+        self.entities.lock().unwrap().clear();
+    }
+
+    /// Removes the given entity from the container, without affecting the entity itself.
+    ///
+    /// The entity can be referred to as any type, such as its concrete type. Returns `true` if the
+    /// container held the entity.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entities, entity::Entities};
+    ///
+    /// trait Visible {}
+    ///
+    /// struct Ghost;
+    /// impl Visible for Ghost {}
+    ///
+    /// let ghost = entity!(Ghost);
+    /// let mut visible = Entities::<dyn Visible>::new();
+    /// visible.add_entities(&entities!(Visible; ghost));
+    ///
+    /// // The ghost keeps existing, but is no longer visible.
+    /// assert!(visible.remove(&ghost));
+    /// assert!(!visible.contains(&ghost));
+    /// ```
+    pub fn remove<U: ?Sized>(&mut self, entity: &Entity<U>) -> bool {
+        let address = address(entity);
+        let entities = &mut self.entities.lock().unwrap();
+        let len = entities.len();
+        entities.retain(|entity| Weak::as_ptr(entity).cast() != address);
+        let removed = entities.len() != len;
+        Self::prune(entities);
+        removed
+    }
+
+    /// Returns `true` if the container holds the given entity.
+    ///
+    /// The entity can be referred to as any type, such as its concrete type. Entities which were
+    /// never added to the container are told apart in constant time, so registering new entities
+    /// does not scan the container.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    #[must_use]
+    pub fn contains<U: ?Sized>(&self, entity: &Entity<U>) -> bool {
+        let address = address(entity);
+        let addresses = self.addresses.lock().unwrap();
+        if !addresses.contains(&(address as usize)) {
+            return false;
+        }
+        let entities = &mut self.entities.lock().unwrap();
+        Self::prune(entities);
+        entities
+            .iter()
+            .any(|entity| Weak::as_ptr(entity).cast() == address)
+    }
+
+    /// Retains only the entities for which the predicate returns `true`.
+    ///
+    /// Dropped entities are removed beforehand and never passed to the predicate.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics. The predicate must not
+    /// access the container itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entity::{Entities, Entity}};
+    ///
+    /// struct Health(u32);
+    ///
+    /// let alive = entity!(Health(10));
+    /// let dead = entity!(Health(0));
+    /// let mut entities = Entities::new();
+    /// entities.add_entities(&[Entity::clone(&alive), Entity::clone(&dead)]);
+    ///
+    /// entities.retain(|entity| entity.read().unwrap().0 > 0);
+    /// assert_eq!(entities.len(), 1);
+    /// ```
+    pub fn retain<F: FnMut(&Entity<T>) -> bool>(&mut self, mut predicate: F) {
+        let entities = &mut self.entities.lock().unwrap();
+        entities.retain(|entity| entity.upgrade().is_some_and(|entity| predicate(&entity)));
+    }
+
+    /// Returns the number of entities in the container, not counting dropped entities.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        let entities = &mut self.entities.lock().unwrap();
+        Self::prune(entities);
+        entities.len()
+    }
+
+    /// Returns `true` if the container holds no entities, not counting dropped entities.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Set whether the entities of the container must be updated serially on the thread running
+    /// the game loop.
+    ///
+    /// This only matters when parallel updates are enabled (see
+    /// `Game::with_parallel_updates`, which requires the `parallel` feature). Marking a container
+    /// is cheaper than returning `true` from the `main_thread` method of each of its entities, as
+    /// the entities are then not checked one by one. The flag is shared by the clones of the
+    /// container. Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::game::Game;
+    ///
+    /// let mut game = Game::new();
+    /// game.update_entities.set_main_thread(true);
+    /// assert!(game.update_entities.is_main_thread());
+    /// ```
+    pub fn set_main_thread(&mut self, main_thread: bool) {
+        self.main_thread.store(main_thread, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the entities of the container must be updated on the thread running the
+    /// game loop, see [`Self::set_main_thread`].
+    #[must_use]
+    pub fn is_main_thread(&self) -> bool {
+        self.main_thread.load(Ordering::Relaxed)
+    }
+
+    /// Returns an iterator over the entities of the container.
+    ///
+    /// The iterator yields strong references collected when it is created, so the container is
+    /// not locked during iteration and can be modified meanwhile. Dropped entities are skipped.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entity::{Entities, Entity}};
+    ///
+    /// struct Coin(u32);
+    ///
+    /// let a = entity!(Coin(1));
+    /// let b = entity!(Coin(2));
+    /// let mut coins = Entities::new();
+    /// coins.add_entities(&[Entity::clone(&a), Entity::clone(&b)]);
+    ///
+    /// let total: u32 = coins.iter().map(|coin| coin.read().unwrap().0).sum();
+    /// assert_eq!(total, 3);
+    /// ```
+    #[must_use]
+    pub fn iter(&self) -> std::vec::IntoIter<Entity<T>> {
+        self.entities
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>()
+            .into_iter()
     }
 
     // Append a new entity to the entity container.
     fn push(&mut self, entity: &Entity<T>) {
-        self.0.lock().unwrap().push(Arc::downgrade(entity));
+        {
+            let mut addresses = self.addresses.lock().unwrap();
+            let mut entities = self.entities.lock().unwrap();
+            addresses.insert(address(entity) as usize);
+            entities.push(Arc::downgrade(entity));
+            // The addresses of removed entities are forgotten once they make up half of the
+            // addresses, keeping the cost of rebuilding the set amortized.
+            if addresses.len() >= 2 * entities.len().max(32) {
+                Self::prune(&mut entities);
+                *addresses = entities
+                    .iter()
+                    .map(|entity| Weak::as_ptr(entity).cast::<()>() as usize)
+                    .collect();
+            }
+        }
+        if let Some(on_add) = &self.on_add {
+            on_add(entity);
+        }
     }
 
     fn prune(entities: &mut Vec<WeakEntity<T>>) {
@@ -148,16 +517,371 @@ impl<T: ?Sized> Entities<T> {
         entities.retain(|entity| entity.upgrade().is_some());
     }
 
+    // Remove every reference to the entity with the given address.
+    pub(crate) fn remove_address(&self, address: *const ()) {
+        self.entities
+            .lock()
+            .unwrap()
+            .retain(|entity| Weak::as_ptr(entity).cast() != address);
+    }
+
+    // Returns the entity with the given address, if it is in the container.
+    pub(crate) fn find_address(&self, address: *const ()) -> Option<Entity<T>> {
+        self.access()
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|entity| Weak::as_ptr(entity).cast() == address)
+            .and_then(Weak::upgrade)
+    }
+
+    // Prune the container and return its lock. A container poisoned by a panicking entity is
+    // recovered, as the game keeps dispatching its entities.
     pub(crate) fn access(&self) -> &Arc<Mutex<Vec<WeakEntity<T>>>> {
-        let entities = &mut self.0.lock().unwrap();
+        let entities = &mut self.entities.lock().unwrap_or_else(PoisonError::into_inner);
         Self::prune(entities);
-        &self.0
+        &self.entities
+    }
+}
+
+impl<T: ?Sized> IntoIterator for &Entities<T> {
+    type Item = Entity<T>;
+    type IntoIter = std::vec::IntoIter<Entity<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Identifier of an entity, made of a slot index and a generation.
+///
+/// Identifiers are assigned by [`EntityIds`]. Once an entity is despawned or dropped, its slot
+/// may be reused by another entity with a greater generation, so an outdated identifier never
+/// refers to a different entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+impl EntityId {
+    /// Index of the identifier's slot.
+    #[must_use]
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Generation of the identifier's slot, incremented every time the slot is reused.
+    #[must_use]
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+
+    /// Returns the identifier as a single integer, for example to serialize it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entity::EntityIds, entity::EntityId};
+    ///
+    /// let ids = EntityIds::default();
+    /// let id = ids.id(&entity!(0));
+    /// assert_eq!(EntityId::from_bits(id.to_bits()), id);
+    /// ```
+    #[must_use]
+    pub fn to_bits(self) -> u64 {
+        u64::from(self.generation) << 32 | u64::from(self.index)
+    }
+
+    /// Returns the identifier represented by the given integer, see [`Self::to_bits`].
+    #[must_use]
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+// A slot of the identifier registry.
+struct Slot {
+    generation: u32,
+    // Address of the entity, if the slot is occupied.
+    address: Option<usize>,
+    // Returns whether the entity is still alive.
+    alive: Box<dyn Fn() -> bool + Send>,
+}
+
+#[derive(Default)]
+struct Slots {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    addresses: HashMap<usize, u32>,
+    // Number of occupied slots after the slots of dropped entities were last freed.
+    pruned: usize,
+}
+
+impl Slots {
+    fn id(&self, index: u32) -> EntityId {
+        EntityId {
+            index,
+            generation: self.slots[index as usize].generation,
+        }
+    }
+
+    fn is_alive(&self, id: EntityId) -> bool {
+        self.slots.get(id.index as usize).is_some_and(|slot| {
+            slot.generation == id.generation && slot.address.is_some() && (slot.alive)()
+        })
+    }
+
+    // Free the given slot, invalidating identifiers referring to it.
+    fn free(&mut self, index: u32) {
+        let slot = &mut self.slots[index as usize];
+        if let Some(address) = slot.address.take() {
+            self.addresses.remove(&address);
+            slot.generation = slot.generation.wrapping_add(1);
+            slot.alive = Box::new(|| false);
+            self.free.push(index);
+        }
+    }
+
+    // Free the slots of every dropped entity.
+    fn prune(&mut self) {
+        for index in 0..self.slots.len() as u32 {
+            if !(self.slots[index as usize].alive)() {
+                self.free(index);
+            }
+        }
+        self.pruned = self.slots.len() - self.free.len();
+    }
+}
+
+/// Registry assigning an [`EntityId`] to entities.
+///
+/// The game owns a registry, which assigns identifiers to entities as they are added to the game,
+/// see [`Game::entity_ids`](crate::game::Game::entity_ids). Despawning an entity through the
+/// game invalidates its identifier. The handle can be cloned and stored in entities.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{entity, entities, error::CtraitResult, game::Game, traits::Update};
+///
+/// struct Enemy;
+/// impl Update for Enemy {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let mut game = Game::new();
+/// let enemy = entity!(Enemy);
+/// game.update_entities.add_entities(&entities!(Update; enemy));
+/// let id = game.entity_ids().get(&enemy).unwrap();
+/// assert!(game.entity_ids().is_alive(id));
+///
+/// game.despawn(&enemy);
+/// // The identifier is now stale.
+/// assert!(!game.entity_ids().is_alive(id));
+/// ```
+#[derive(Clone, Default)]
+pub struct EntityIds(Arc<Mutex<Slots>>);
+
+impl EntityIds {
+    /// Returns the identifier of the given entity, assigning one if it has none.
+    ///
+    /// The identifier does not depend on the type the entity is referred to as, meaning an
+    /// entity has the same identifier in every container.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn id<T: ?Sized + Send + Sync + 'static>(&self, entity: &Entity<T>) -> EntityId {
+        let address = address(entity) as usize;
+        let mut slots = self.0.lock().unwrap();
+        if let Some(&index) = slots.addresses.get(&address) {
+            if (slots.slots[index as usize].alive)() {
+                return slots.id(index);
+            }
+            // The entity was dropped and its address reused by the given entity.
+            slots.free(index);
+        }
+        // Dropped entities are only found by sweeping every slot, so the sweep waits until the
+        // number of slots doubled since the last one, keeping its cost amortized.
+        if slots.free.is_empty() && slots.slots.len() >= slots.pruned * 2 {
+            slots.prune();
+        }
+        let weak = Arc::downgrade(entity);
+        let alive = Box::new(move || weak.strong_count() > 0);
+        let index = if let Some(index) = slots.free.pop() {
+            let slot = &mut slots.slots[index as usize];
+            slot.address = Some(address);
+            slot.alive = alive;
+            index
+        } else {
+            slots.slots.push(Slot {
+                generation: 0,
+                address: Some(address),
+                alive,
+            });
+            (slots.slots.len() - 1) as u32
+        };
+        slots.addresses.insert(address, index);
+        slots.id(index)
+    }
+
+    /// Returns the identifier of the given entity, if it has one.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn get<T: ?Sized>(&self, entity: &Entity<T>) -> Option<EntityId> {
+        let slots = self.0.lock().unwrap();
+        let index = *slots.addresses.get(&(address(entity) as usize))?;
+        let id = slots.id(index);
+        slots.is_alive(id).then_some(id)
+    }
+
+    /// Returns `true` if the identifier refers to an entity which was neither despawned nor
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.0.lock().unwrap().is_alive(id)
+    }
+
+    // Address of the entity the identifier refers to, if it is alive.
+    pub(crate) fn address(&self, id: EntityId) -> Option<*const ()> {
+        let slots = self.0.lock().unwrap();
+        if slots.is_alive(id) {
+            slots.slots[id.index as usize]
+                .address
+                .map(|address| address as *const ())
+        } else {
+            None
+        }
+    }
+
+    // Invalidate the identifier of the entity with the given address.
+    pub(crate) fn remove_address(&self, address: *const ()) {
+        let mut slots = self.0.lock().unwrap();
+        if let Some(&index) = slots.addresses.get(&(address as usize)) {
+            slots.free(index);
+        }
+    }
+}
+
+/// Entity container holding a [`Vec`] of strong references to entities.
+///
+/// Unlike [`Entities`], this container keeps its entities alive. An entity is considered
+/// released once the container holds the only remaining reference to it, meaning every other
+/// owner has dropped it. Released entities can then be notified before they are finally dropped.
+///
+/// This structure is thread-safe.
+#[derive(Debug)]
+pub struct OwnedEntities<T: ?Sized>(Arc<Mutex<Vec<Entity<T>>>>);
+
+impl<T: ?Sized> Default for OwnedEntities<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized> Clone for OwnedEntities<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T: ?Sized> OwnedEntities<T> {
+    /// Constructs a new owning entity container.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity::OwnedEntities, traits::Destroy};
+    ///
+    /// let entities = OwnedEntities::<dyn Destroy>::new();
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    /// Add entities from a given entity slice.
+    ///
+    /// It is recommended to create the entity slice with [`entities`].
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    pub fn add_entities(&mut self, other: &[Entity<T>]) {
+        self.0
+            .lock()
+            .unwrap()
+            .extend(other.iter().map(Entity::clone));
+    }
+
+    /// Returns `true` if the container holds the given entity.
+    ///
+    /// The entity can be referred to as any type, such as its concrete type.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    #[must_use]
+    pub fn contains<U: ?Sized>(&self, entity: &Entity<U>) -> bool {
+        let address = address(entity);
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|entity| self::address(entity) == address)
+    }
+
+    /// Clears the entity container, removing all entities without releasing them.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the container panics.
+    pub fn clear(&mut self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    // Remove and return every reference to the entity with the given address.
+    pub(crate) fn remove_address(&self, address: *const ()) -> Vec<Entity<T>> {
+        let mut entities = self.0.lock().unwrap();
+        let (removed, kept) = entities
+            .drain(..)
+            .partition(|entity| self::address(entity) == address);
+        *entities = kept;
+        removed
+    }
+
+    // Remove and return all entities which are only referred to by the container.
+    pub(crate) fn release(&self) -> Vec<Entity<T>> {
+        let mut entities = self.0.lock().unwrap();
+        let mut released = Vec::new();
+        let mut index = 0;
+        while index < entities.len() {
+            if Arc::strong_count(&entities[index]) == 1 {
+                released.push(entities.swap_remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        released
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Arc, Entities};
+    use super::{Arc, Entities, EntityId, EntityIds, OwnedEntities};
 
     // Test struct to create test entity.
     struct Test;
@@ -171,20 +895,20 @@ mod tests {
             }
         }
         let entity = entity!(Number(3));
-        assert_eq!(entity.lock().unwrap().value(), 3);
+        assert_eq!(entity.read().unwrap().value(), 3);
     }
 
     #[test]
     fn entities_new() {
         let entities = Entities::<Test>::new();
-        assert!(entities.0.lock().unwrap().is_empty());
+        assert!(entities.entities.lock().unwrap().is_empty());
     }
 
     #[test]
     fn entities_clone() {
         let entities = Entities::<Test>::default();
         let _entities_clone = Entities::clone(&entities);
-        assert_eq!(Arc::strong_count(&entities.0), 2);
+        assert_eq!(Arc::strong_count(&entities.entities), 2);
     }
 
     #[test]
@@ -192,7 +916,7 @@ mod tests {
         let entity = entity!(Test {});
         let mut entities = Entities::default();
         entities.push(&entity);
-        assert_eq!(entities.0.lock().unwrap().len(), 1);
+        assert_eq!(entities.entities.lock().unwrap().len(), 1);
     }
 
     #[test]
@@ -201,16 +925,34 @@ mod tests {
         let b = entity!(Test {});
         let mut entities = Entities::default();
         entities.add_entities(&[a, b]);
-        assert_eq!(entities.0.lock().unwrap().len(), 2);
+        assert_eq!(entities.entities.lock().unwrap().len(), 2);
     }
 
     #[test]
     fn entities_clear() {
         let entity = entity!(Test {});
         let mut entities = Entities::default();
-        entities.0.lock().unwrap().push(Arc::downgrade(&entity));
+        entities
+            .entities
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(&entity));
         entities.clear();
-        assert!(entities.0.lock().unwrap().is_empty());
+        assert!(entities.entities.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn entities_remove_address() {
+        let a = entity!(Test {});
+        let b = entity!(Test {});
+        let mut entities = Entities::default();
+        entities.add_entities(&[Arc::clone(&a), Arc::clone(&b), Arc::clone(&a)]);
+        entities.remove_address(super::address(&a));
+        assert_eq!(entities.entities.lock().unwrap().len(), 1);
+        let mut owned = OwnedEntities::default();
+        owned.add_entities(&[Arc::clone(&a), Arc::clone(&b)]);
+        assert_eq!(owned.remove_address(super::address(&b)).len(), 1);
+        assert_eq!(owned.0.lock().unwrap().len(), 1);
     }
 
     #[test]
@@ -234,7 +976,7 @@ mod tests {
         // Intentional avoidance of EntityContainer::add_entities. This function is tested
         // elsewhere.
         entities
-            .0
+            .entities
             .lock()
             .unwrap()
             .extend_from_slice(&[Arc::downgrade(&a), Arc::downgrade(&b)]);
@@ -242,9 +984,160 @@ mod tests {
         // method call.
         drop(a);
         entities.access();
-        assert_eq!(entities.0.lock().unwrap().len(), 1);
+        assert_eq!(entities.entities.lock().unwrap().len(), 1);
         drop(b);
         entities.access();
+        assert!(entities.entities.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn entities_remove() {
+        let a = entity!(Test {});
+        let b = entity!(Test {});
+        let mut entities = Entities::new();
+        entities.add_entities(&[Arc::clone(&a), Arc::clone(&b)]);
+        assert!(entities.remove(&a));
+        assert!(!entities.remove(&a));
+        assert!(!entities.contains(&a));
+        assert!(entities.contains(&b));
+        assert_eq!(entities.len(), 1);
+        // The removed entity itself is unaffected.
+        assert_eq!(Arc::strong_count(&a), 1);
+    }
+
+    #[test]
+    fn entities_contains_addresses() {
+        let kept = entity!(Test {});
+        let mut entities = Entities::new();
+        entities.add_entities(&[Arc::clone(&kept)]);
+        for _ in 0..1000 {
+            let dropped = entity!(Test {});
+            assert!(!entities.contains(&dropped));
+            entities.add_entities(&[Arc::clone(&dropped)]);
+            assert!(entities.contains(&dropped));
+        }
+        assert!(entities.contains(&kept));
+        // The addresses of dropped entities are forgotten.
+        assert!(entities.addresses.lock().unwrap().len() <= 64);
+    }
+
+    #[test]
+    fn entities_remove_absent() {
+        let a = entity!(Test {});
+        let b = entity!(Test {});
+        let c = entity!(Test {});
+        let mut entities = Entities::new();
+        entities.add_entities(&[Arc::clone(&a), Arc::clone(&b)]);
+        // Pruning the dropped entity does not count as removing c.
+        drop(b);
+        assert!(!entities.remove(&c));
+        assert!(entities.contains(&a));
+    }
+
+    #[test]
+    fn entities_retain() {
+        let a = entity!(1);
+        let b = entity!(2);
+        let c = entity!(3);
+        let mut entities = Entities::new();
+        entities.add_entities(&[Arc::clone(&a), Arc::clone(&b), Arc::clone(&c)]);
+        drop(c);
+        let mut visited = 0;
+        entities.retain(|entity| {
+            visited += 1;
+            *entity.read().unwrap() % 2 == 0
+        });
+        assert_eq!(visited, 2);
+        assert!(entities.contains(&b));
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn entities_iter() {
+        let a = entity!(1);
+        let b = entity!(2);
+        let mut entities = Entities::new();
+        entities.add_entities(&[Arc::clone(&a), Arc::clone(&b)]);
+        drop(a);
+        assert_eq!(entities.len(), 1);
+        assert!(!entities.is_empty());
+        let values: Vec<i32> = (&entities)
+            .into_iter()
+            .map(|entity| *entity.read().unwrap())
+            .collect();
+        assert_eq!(values, [2]);
+        // The container can be modified while iterating.
+        for entity in &entities {
+            entities.clone().remove(&entity);
+        }
+        assert!(entities.is_empty());
+    }
+
+    #[test]
+    fn entity_ids_stable() {
+        let ids = EntityIds::default();
+        let a = entity!(Test {});
+        let b = entity!(Test {});
+        let id = ids.id(&a);
+        // The identifier does not depend on how the entity is referred to.
+        assert_eq!(
+            ids.id(&(Arc::clone(&a) as super::Entity<dyn Sync + Send>)),
+            id
+        );
+        assert_ne!(ids.id(&b), id);
+        assert_eq!(ids.get(&a), Some(id));
+        assert!(ids.is_alive(id));
+    }
+
+    #[test]
+    fn entity_ids_stale() {
+        let ids = EntityIds::default();
+        let a = entity!(Test {});
+        let id = ids.id(&a);
+        ids.remove_address(super::address(&a));
+        assert!(!ids.is_alive(id));
+        assert_eq!(ids.get(&a), None);
+        // A new identifier reuses the slot with a greater generation.
+        let new_id = ids.id(&a);
+        assert_eq!(new_id.index(), id.index());
+        assert_eq!(new_id.generation(), id.generation() + 1);
+
+        drop(a);
+        assert!(!ids.is_alive(new_id));
+        assert_eq!(EntityId::from_bits(new_id.to_bits()), new_id);
+    }
+
+    #[test]
+    fn entity_ids_prune() {
+        let ids = EntityIds::default();
+        let alive: Vec<_> = (0..10).map(|_| entity!(Test {})).collect();
+        for entity in &alive {
+            ids.id(entity);
+        }
+        for _ in 0..1000 {
+            ids.id(&entity!(Test {}));
+        }
+        // The slots of dropped entities are reused.
+        assert!(ids.0.lock().unwrap().slots.len() <= alive.len() * 2);
+        assert!(alive.iter().all(|entity| ids.get(entity).is_some()));
+    }
+
+    #[test]
+    fn owned_entities_release() {
+        let a = entity!(Test {});
+        let b = entity!(Test {});
+        let mut entities = OwnedEntities::default();
+        entities.add_entities(&[Arc::clone(&a), Arc::clone(&b)]);
+        assert!(entities.release().is_empty());
+        // The container keeps a alive after it is dropped elsewhere.
+        let weak = Arc::downgrade(&a);
+        drop(a);
+        assert!(weak.upgrade().is_some());
+        assert_eq!(entities.release().len(), 1);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(entities.0.lock().unwrap().len(), 1);
+        drop(b);
+        assert_eq!(entities.release().len(), 1);
         assert!(entities.0.lock().unwrap().is_empty());
     }
 }
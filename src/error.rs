@@ -14,6 +14,7 @@ pub enum CtraitError {
     IntegerOr(IntegerOrSdlError),
     UpdateTexture(UpdateTextureError),
     WindowBuild(WindowBuildError),
+    Entity(Box<EntityError>),
     Other(String),
 }
 
@@ -25,11 +26,46 @@ impl Display for CtraitError {
             CtraitError::IntegerOr(ref e) => e.fmt(f),
             CtraitError::UpdateTexture(ref e) => e.fmt(f),
             CtraitError::WindowBuild(ref e) => e.fmt(f),
+            CtraitError::Entity(ref e) => e.fmt(f),
             CtraitError::Other(ref e) => e.fmt(f),
         }
     }
 }
 
+/// Error returned by one of the methods the game calls on an entity.
+#[derive(Debug)]
+pub struct EntityError {
+    /// Name of the entity's type, as returned by
+    /// [`TypeName::type_name`](crate::traits::TypeName::type_name).
+    pub type_name: &'static str,
+    /// Name of the method which returned the error, such as `"update"`.
+    pub phase: &'static str,
+    /// The error returned by the entity.
+    pub error: CtraitError,
+}
+
+impl Error for EntityError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl Display for EntityError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "entity of type `{}` failed in {}: {}",
+            self.type_name, self.phase, self.error
+        )
+    }
+}
+
+impl From<EntityError> for CtraitError {
+    fn from(err: EntityError) -> Self {
+        Self::Entity(Box::new(err))
+    }
+}
+
 impl From<IntegerOrSdlError> for CtraitError {
     fn from(err: IntegerOrSdlError) -> Self {
         Self::IntegerOr(err)
@@ -56,14 +92,16 @@ impl From<String> for CtraitError {
 
 #[cfg(test)]
 mod tests {
-    use super::{CtraitError, IntegerOrSdlError, UpdateTextureError, WindowBuildError};
+    use super::{
+        CtraitError, EntityError, IntegerOrSdlError, UpdateTextureError, WindowBuildError,
+    };
 
     macro_rules! assert_error_display {
         ($variant:ident, $error:expr) => {
-            format!("{}", CtraitError::$variant($error))
+            let _ = format!("{}", CtraitError::$variant($error));
         };
         ($variant:ident, $error:expr, $expected:expr) => {
-            assert_eq!(assert_error_display!($variant, $error), $expected);
+            assert_eq!(format!("{}", CtraitError::$variant($error)), $expected);
         };
     }
 
@@ -82,6 +120,19 @@ mod tests {
         assert_error_display!(WindowBuild, WindowBuildError::HeightOverflows(1));
     }
 
+    #[test]
+    fn error_display_entity() {
+        assert_error_display!(
+            Entity,
+            Box::new(EntityError {
+                type_name: "Player",
+                phase: "update",
+                error: CtraitError::Other(String::from("error")),
+            }),
+            "entity of type `Player` failed in update: error"
+        );
+    }
+
     #[test]
     fn error_display_other() {
         assert_error_display!(Other, String::from("error"), "error");
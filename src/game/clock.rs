@@ -0,0 +1,105 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+// Pause state and time scale of a game.
+// It is shared with the fixed update thread.
+#[derive(Debug, Clone)]
+pub(crate) struct Clock {
+    paused: Arc<AtomicBool>,
+    // Bits of an f32.
+    time_scale: Arc<AtomicU32>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            paused: Arc::new(AtomicBool::new(false)),
+            time_scale: Arc::new(AtomicU32::new(1.0_f32.to_bits())),
+        }
+    }
+}
+
+impl Clock {
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub(crate) fn time_scale(&self) -> f32 {
+        f32::from_bits(self.time_scale.load(Ordering::SeqCst))
+    }
+
+    pub(crate) fn set_time_scale(&self, time_scale: f32) {
+        self.time_scale
+            .store(time_scale.max(0.0).to_bits(), Ordering::SeqCst);
+    }
+
+    // Scale the given real delta. Returns 0 if paused.
+    pub(crate) fn scale(&self, delta: f32) -> f32 {
+        if self.is_paused() {
+            0.0
+        } else {
+            delta * self.time_scale()
+        }
+    }
+
+    // Deltas for entities following the clock and for entities ignoring it.
+    pub(crate) fn deltas(&self, delta: f32) -> Deltas {
+        Deltas {
+            scaled: (!self.is_paused()).then(|| self.scale(delta)),
+            unscaled: Some(delta),
+        }
+    }
+}
+
+// Delta passed to entities, depending on whether they use scaled or unscaled time.
+// Entities are skipped if their delta is None.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Deltas {
+    pub(crate) scaled: Option<f32>,
+    pub(crate) unscaled: Option<f32>,
+}
+
+impl Deltas {
+    pub(crate) fn get(self, unscaled_time: bool) -> Option<f32> {
+        if unscaled_time {
+            self.unscaled
+        } else {
+            self.scaled
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, Deltas};
+
+    #[test]
+    fn clock_deltas() {
+        let clock = Clock::default();
+        clock.set_time_scale(0.5);
+        assert_eq!(
+            clock.deltas(0.2),
+            Deltas {
+                scaled: Some(0.1),
+                unscaled: Some(0.2)
+            }
+        );
+        clock.set_paused(true);
+        assert_eq!(clock.scale(0.2), 0.0);
+        assert_eq!(clock.deltas(0.2).get(false), None);
+        assert_eq!(clock.deltas(0.2).get(true), Some(0.2));
+    }
+
+    #[test]
+    fn clock_negative_time_scale() {
+        let clock = Clock::default();
+        clock.set_time_scale(-1.0);
+        assert_eq!(clock.time_scale(), 0.0);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    entity::{Entities, Entity},
+    game::Game,
+    scene::SceneEntities,
+    traits::{Destroy, FixedUpdate, Interactive, LateUpdate, PreUpdate, Renderable, Start, Update},
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A trait object type which has an entity container in [`Game`] and in [`SceneEntities`].
+///
+/// This trait is sealed. It is implemented for `dyn PreUpdate`, `dyn Update`, `dyn LateUpdate`,
+/// `dyn FixedUpdate`, `dyn Renderable` and `dyn Interactive`, and used to select a container
+/// with a type parameter, such as with [`Game::lookup`].
+pub trait EntityTrait: sealed::Sealed {
+    /// The container of the game for this trait.
+    fn game_entities(game: &Game) -> &Entities<Self>;
+
+    /// The container of a scene for this trait.
+    fn scene_entities(entities: &SceneEntities) -> &Entities<Self>;
+}
+
+/// A trait object type which has an entity container in [`Game`], including the lifecycle
+/// containers.
+///
+/// This trait is sealed. It is implemented for the trait object types of every container of the
+/// game, and used by [`Game::register`].
+pub trait Registrable: sealed::Sealed + Send + Sync + 'static {
+    /// Add the entity to the container of the game for this trait, unless the container already
+    /// holds it. Returns `true` if the entity was added.
+    fn register(game: &mut Game, entity: &Entity<Self>) -> bool;
+}
+
+macro_rules! entity_trait {
+    ($($name:ident => $field:ident),+) => {
+        $(
+            impl sealed::Sealed for dyn $name {}
+
+            impl EntityTrait for dyn $name {
+                fn game_entities(game: &Game) -> &Entities<Self> {
+                    &game.$field
+                }
+
+                fn scene_entities(entities: &SceneEntities) -> &Entities<Self> {
+                    &entities.$field
+                }
+            }
+        )+
+        registrable!($($name => $field),+);
+    };
+}
+
+macro_rules! registrable {
+    ($($name:ident => $field:ident),+) => {
+        $(
+            impl Registrable for dyn $name {
+                fn register(game: &mut Game, entity: &Entity<Self>) -> bool {
+                    if game.$field.contains(entity) {
+                        false
+                    } else {
+                        game.$field.add_entities(&[Entity::clone(entity)]);
+                        true
+                    }
+                }
+            }
+        )+
+    };
+}
+
+entity_trait!(
+    PreUpdate => pre_update_entities,
+    Update => update_entities,
+    LateUpdate => late_update_entities,
+    FixedUpdate => fixed_update_entities,
+    Renderable => renderable_entities,
+    Interactive => interactive_entities
+);
+
+impl sealed::Sealed for dyn Start {}
+impl sealed::Sealed for dyn Destroy {}
+
+registrable!(Start => start_entities, Destroy => destroy_entities);
@@ -0,0 +1,191 @@
+use std::sync::{Arc, Mutex};
+
+/// Reason why [`Game::start`](crate::game::Game::start) returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The window was closed by the user.
+    WindowClosed,
+    /// Quit was requested through [`GameControl::quit`].
+    Quit,
+    /// A restart was requested through [`GameControl::restart`].
+    ///
+    /// It is up to the caller to reset its entities and start the game again.
+    Restart,
+}
+
+// Requests made through a control handle which have not been applied yet.
+#[derive(Debug, Default)]
+pub(crate) struct ControlRequests {
+    pub(crate) exit: Option<ExitReason>,
+    pub(crate) paused: Option<bool>,
+    pub(crate) time_scale: Option<f32>,
+    pub(crate) timestep: Option<i64>,
+    pub(crate) toggle_fullscreen: bool,
+}
+
+/// Handle to control a running [`Game`](crate::game::Game).
+///
+/// The handle can be cloned and stored in entities. Requests are not applied immediately; they
+/// are applied by the game at the end of the current frame, after all updates have run and
+/// before rendering.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{
+///     error::CtraitResult,
+///     game::{ExitReason, Game, GameControl},
+///     traits::Update,
+/// };
+///
+/// struct Lives {
+///     remaining: u32,
+///     control: GameControl,
+/// }
+///
+/// impl Update for Lives {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         if self.remaining == 0 {
+///             // Game over.
+///             self.control.quit();
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let game = Game::new();
+/// let lives = Lives {
+///     remaining: 3,
+///     control: game.control(),
+/// };
+/// ```
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Default, Clone)]
+pub struct GameControl(Arc<Mutex<ControlRequests>>);
+
+impl GameControl {
+    /// Request the game to stop with [`ExitReason::Quit`].
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn quit(&self) {
+        self.0.lock().unwrap().exit = Some(ExitReason::Quit);
+    }
+
+    /// Request the game to stop with [`ExitReason::Restart`].
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn restart(&self) {
+        self.0.lock().unwrap().exit = Some(ExitReason::Restart);
+    }
+
+    /// Request the game to be paused.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn pause(&self) {
+        self.0.lock().unwrap().paused = Some(true);
+    }
+
+    /// Request the game to be resumed.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn resume(&self) {
+        self.0.lock().unwrap().paused = Some(false);
+    }
+
+    /// Request the time scale of the game to be changed.
+    ///
+    /// See [`Game::set_time_scale`](crate::game::Game::set_time_scale).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the time scale is infinite or NaN. It might also panic if another
+    /// user of the handle panics.
+    pub fn set_time_scale(&self, time_scale: f32) {
+        assert!(
+            time_scale.is_finite(),
+            "time scale must be finite, got {}",
+            time_scale
+        );
+        self.0.lock().unwrap().time_scale = Some(time_scale);
+    }
+
+    /// Request the delay in milliseconds between
+    /// [`FixedUpdate::fixed_update`](crate::traits::FixedUpdate::fixed_update) method calls to be
+    /// changed.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the timestep is not positive. It might also panic if another user
+    /// of the handle panics.
+    pub fn set_timestep(&self, timestep: i64) {
+        assert!(timestep > 0, "timestep must be positive, got {}", timestep);
+        self.0.lock().unwrap().timestep = Some(timestep);
+    }
+
+    /// Request the window to be toggled between fullscreen and windowed mode.
+    ///
+    /// This has no effect when the game is driven without a window.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn toggle_fullscreen(&self) {
+        let mut requests = self.0.lock().unwrap();
+        requests.toggle_fullscreen = !requests.toggle_fullscreen;
+    }
+
+    // Take all pending requests, leaving none behind.
+    pub(crate) fn take(&self) -> ControlRequests {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExitReason, GameControl};
+
+    #[test]
+    fn game_control_take() {
+        let control = GameControl::default();
+        let clone = GameControl::clone(&control);
+        clone.pause();
+        clone.quit();
+        clone.set_timestep(5);
+        let requests = control.take();
+        assert_eq!(requests.exit, Some(ExitReason::Quit));
+        assert_eq!(requests.paused, Some(true));
+        assert_eq!(requests.timestep, Some(5));
+        assert!(!requests.toggle_fullscreen);
+        // Requests are cleared once taken.
+        assert!(control.take().exit.is_none());
+    }
+
+    #[test]
+    fn game_control_toggle_fullscreen() {
+        let control = GameControl::default();
+        control.toggle_fullscreen();
+        control.toggle_fullscreen();
+        // Two toggles in the same frame cancel out.
+        assert!(!control.take().toggle_fullscreen);
+    }
+
+    #[test]
+    #[should_panic(expected = "timestep must be positive")]
+    fn game_control_zero_timestep() {
+        GameControl::default().set_timestep(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "time scale must be finite")]
+    fn game_control_nan_time_scale() {
+        GameControl::default().set_time_scale(f32::NAN);
+    }
+}
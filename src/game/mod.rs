@@ -0,0 +1,2437 @@
+//! Main storage for entity containers.
+mod clock;
+mod container;
+mod control;
+mod pacer;
+mod panic;
+mod stats;
+
+pub use container::{EntityTrait, Registrable};
+pub use control::{ExitReason, GameControl};
+pub use panic::PanicPolicy;
+pub(crate) use panic::{Dispatched, Isolation};
+pub use stats::{EntityCounts, FrameStats, PhaseTimes};
+
+use crate::{
+    bus::EventBus,
+    command::Commands,
+    ecs::{SystemPhase, Systems, World},
+    entity::{self, Entities, Entity, EntityId, EntityIds, OwnedEntities},
+    error::{CtraitResult, EntityError},
+    graphics::{RenderContext, Renderer, TextureManager},
+    hierarchy::Hierarchy,
+    registry::Registry,
+    scene::{Scene, SceneControl, SceneEntities, ScenePhase, SceneStack},
+    scheduler::Scheduler,
+    traits::{
+        Destroy, FixedUpdate, Interactive, LateUpdate, PreUpdate, Renderable, Spawn, Start, Update,
+    },
+};
+use chrono::Duration;
+use clock::{Clock, Deltas};
+use pacer::FramePacer;
+use sdl2::{event::Event, video::FullscreenType};
+use stats::lap;
+use std::{
+    sync::PoisonError,
+    time::{self, Instant},
+};
+use timer::{Guard, Timer};
+
+/// Strategy used to call [`FixedUpdate::fixed_update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FixedUpdateMode {
+    /// Fixed updates are run on the main thread with an accumulator.
+    ///
+    /// Every frame, the elapsed time is added to an accumulator and as many fixed steps as fit
+    /// into it are run before [`Update::update`]. Each step receives exactly the configured
+    /// timestep as its `delta`, which makes the simulation deterministic. The remaining fraction
+    /// of a step is exposed to renderables through [`RenderContext::alpha`].
+    ///
+    /// Scaled time and unscaled time are accumulated separately. Entities using scaled time are
+    /// stepped as often as the scaled time allows, while entities using unscaled time (see
+    /// [`FixedUpdate::unscaled_time`]) are stepped according to real time.
+    #[default]
+    Accumulated,
+    /// Fixed updates are run on a separate timer thread.
+    ///
+    /// Fixed and standard updates run concurrently and contend for the same entities.
+    Threaded,
+}
+
+/// Game manager.
+///
+/// The game manager holds multiple [`Entities`], each representing
+/// [`Weak`](std::sync::Weak) pointers to entities.
+///
+/// In addition, the game manages a stack of [`Scene`]s, each owning their own entity containers.
+/// Entities registered directly in the game's containers are always active, regardless of the
+/// scene stack. They are updated before the entities of any scene.
+///
+/// Lifecycle containers ([`Self::start_entities`] and [`Self::destroy_entities`]) are not part
+/// of scenes; entities of any scene can be registered in them.
+///
+/// # Phase Order
+///
+/// Every frame of [`Self::start`] runs the following phases in order:
+///
+/// 1. [`Interactive::on_event`] for every polled event.
+/// 2. [`Start::start`] for newly registered [`Self::start_entities`].
+/// 3. [`Destroy::destroy`] for released [`Self::destroy_entities`].
+/// 4. [`PreUpdate::pre_update`].
+/// 5. [`FixedUpdate::fixed_update`], as many times as fixed steps are due (only with
+///    [`FixedUpdateMode::Accumulated`]).
+/// 6. Callbacks of due timers scheduled through [`Self::scheduler`].
+/// 7. [`Update::update`].
+/// 8. [`LateUpdate::late_update`], followed by the propagation of world transforms through
+///    the [`Hierarchy`].
+/// 9. Events emitted through [`Self::event_bus`] are delivered to their subscribers, see
+///    [`EventBus`].
+/// 10. Requested scene transitions and [`GameControl`] requests are applied.
+/// 11. [`Renderable::render`].
+///
+/// Within each phase, the game's own containers are processed first, followed by the containers
+/// of the active scenes from bottom to top. Entities within a container are processed in the
+/// order they were added. Rendering follows this order only among entities with the same
+/// [`Renderable::layer`] and [`Renderable::z_index`], see
+/// [`Layers`](crate::graphics::Layers).
+///
+/// Systems added with [`Self::add_system`] are run in the phases 4, 5, 7 and 8, after the
+/// entities of the phase.
+///
+/// Commands queued through [`Self::commands`] are applied at the end of each of the phases 1 to
+/// 9, and after every single fixed step.
+pub struct Game {
+    /// Entities implementing [`PreUpdate`] trait.
+    pub pre_update_entities: Entities<dyn PreUpdate>,
+    /// Entities implementing [`Update`] trait.
+    pub update_entities: Entities<dyn Update>,
+    /// Entities implementing [`LateUpdate`] trait.
+    pub late_update_entities: Entities<dyn LateUpdate>,
+    /// Entities implementing [`FixedUpdate`] trait.
+    pub fixed_update_entities: Entities<dyn FixedUpdate>,
+    /// Entities implementing [`Renderable`] trait.
+    pub renderable_entities: Entities<dyn Renderable>,
+    /// Entities implementing [`Interactive`] trait.
+    pub interactive_entities: Entities<dyn Interactive>,
+    /// Entities implementing [`Start`] trait.
+    ///
+    /// Entities are removed from the container once they have been started.
+    pub start_entities: Entities<dyn Start>,
+    /// Entities implementing [`Destroy`] trait.
+    ///
+    /// The container keeps its entities alive. Once every other reference to an entity has been
+    /// dropped, [`Destroy::destroy`] is called and the entity is dropped.
+    pub destroy_entities: OwnedEntities<dyn Destroy>,
+    timestep: i64,
+    fixed_update_mode: FixedUpdateMode,
+    max_fixed_steps: u32,
+    accumulator: f32,
+    unscaled_accumulator: f32,
+    scenes: SceneStack,
+    scene_control: SceneControl,
+    control: GameControl,
+    commands: Commands,
+    ids: EntityIds,
+    scheduler: Scheduler,
+    hierarchy: Hierarchy,
+    registry: Registry,
+    event_bus: EventBus,
+    clock: Clock,
+    stats: FrameStats,
+    stats_overlay: bool,
+    parallel: bool,
+    panic_policy: PanicPolicy,
+    error_handler: Option<Box<dyn FnMut(EntityError) + Send>>,
+    error: Option<EntityError>,
+    world: World,
+    systems: Systems,
+    exit: Option<ExitReason>,
+    toggle_fullscreen: bool,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    /// Default number of milliseconds between [`FixedUpdate::fixed_update`] method calls.
+    pub const DEFAULT_TIMESTEP: i64 = ((1.0 / 50.0) * 1000.0) as i64;
+
+    /// Default maximum number of fixed steps run in a single frame.
+    pub const DEFAULT_MAX_FIXED_STEPS: u32 = 5;
+
+    /// Create a new game.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::game::Game;
+    ///
+    /// let game = Game::new();
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        let ids = EntityIds::default();
+        Self {
+            pre_update_entities: Entities::with_ids(&ids),
+            update_entities: Entities::with_ids(&ids),
+            late_update_entities: Entities::with_ids(&ids),
+            fixed_update_entities: Entities::with_ids(&ids),
+            renderable_entities: Entities::with_ids(&ids),
+            interactive_entities: Entities::with_ids(&ids),
+            start_entities: Entities::with_ids(&ids),
+            destroy_entities: OwnedEntities::default(),
+            timestep: Self::DEFAULT_TIMESTEP,
+            fixed_update_mode: FixedUpdateMode::default(),
+            max_fixed_steps: Self::DEFAULT_MAX_FIXED_STEPS,
+            accumulator: 0.0,
+            unscaled_accumulator: 0.0,
+            scenes: SceneStack::default(),
+            scene_control: SceneControl::default(),
+            control: GameControl::default(),
+            commands: Commands::default(),
+            ids,
+            scheduler: Scheduler::default(),
+            hierarchy: Hierarchy::default(),
+            registry: Registry::default(),
+            event_bus: EventBus::default(),
+            clock: Clock::default(),
+            exit: None,
+            toggle_fullscreen: false,
+            stats: FrameStats::default(),
+            stats_overlay: false,
+            parallel: false,
+            panic_policy: PanicPolicy::default(),
+            error_handler: None,
+            error: None,
+            world: World::new(),
+            systems: Systems::default(),
+        }
+    }
+
+    /// Customize the delay in milliseconds between [`FixedUpdate::fixed_update`] method calls.
+    ///
+    /// Default timestep is equal to [`Self::DEFAULT_TIMESTEP`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the timestep is not positive.
+    #[must_use]
+    pub fn with_timestep(mut self, timestep: i64) -> Self {
+        // Fixed steps are counted by dividing the accumulated time by the timestep.
+        assert!(timestep > 0, "timestep must be positive, got {}", timestep);
+        self.timestep = timestep;
+        self
+    }
+
+    /// Customize how [`FixedUpdate::fixed_update`] method calls are scheduled.
+    ///
+    /// Default mode is [`FixedUpdateMode::Accumulated`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::game::{FixedUpdateMode, Game};
+    ///
+    /// // Opt in to running fixed updates on a separate timer thread.
+    /// let game = Game::new().with_fixed_update_mode(FixedUpdateMode::Threaded);
+    /// ```
+    #[must_use]
+    pub fn with_fixed_update_mode(mut self, mode: FixedUpdateMode) -> Self {
+        self.fixed_update_mode = mode;
+        self
+    }
+
+    /// Construct game with parallel updates enabled or disabled.
+    ///
+    /// When enabled, the entities of each [`Update`] and [`FixedUpdate`] container are spread over
+    /// a work-stealing thread pool instead of being updated one after the other. Containers are
+    /// still processed in the [phase order](Self#phase-order), one at a time. Entities returning
+    /// `true` from [`Update::main_thread`] or [`FixedUpdate::main_thread`] are updated serially on
+    /// the calling thread, in container order, after the entities of the same container that
+    /// were updated in parallel. Whole containers can be updated serially with
+    /// [`Entities::set_main_thread`].
+    ///
+    /// Other phases are always dispatched serially. Default is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::game::Game;
+    ///
+    /// let game = Game::new().with_parallel_updates(true);
+    /// ```
+    #[cfg(feature = "parallel")]
+    #[must_use]
+    pub fn with_parallel_updates(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Customize how panics of entities are handled.
+    ///
+    /// Default policy is [`PanicPolicy::Abort`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::game::{Game, PanicPolicy};
+    ///
+    /// // Keep the game running without the entities which panicked.
+    /// let game = Game::new().with_panic_policy(PanicPolicy::Remove);
+    /// ```
+    #[must_use]
+    pub fn with_panic_policy(mut self, panic_policy: PanicPolicy) -> Self {
+        self.panic_policy = panic_policy;
+        self
+    }
+
+    /// Customize how errors returned by entities are handled.
+    ///
+    /// Errors are passed to the handler at the end of the phase they were returned in (see the
+    /// [phase order](Self#phase-order)), and the game keeps running. Without a handler, the first
+    /// error stops [`Self::start`], which returns it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::game::Game;
+    ///
+    /// let game = Game::new().with_error_handler(|error| eprintln!("{}", error));
+    /// ```
+    #[must_use]
+    pub fn with_error_handler<F: FnMut(EntityError) + Send + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.error_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Customize the maximum number of fixed steps run in a single frame.
+    ///
+    /// If a frame takes long enough that more steps are due, the excess time is discarded rather
+    /// than carried over. This prevents a slow frame from causing ever slower frames.
+    /// Only used with [`FixedUpdateMode::Accumulated`].
+    ///
+    /// Default is equal to [`Self::DEFAULT_MAX_FIXED_STEPS`].
+    #[must_use]
+    pub fn with_max_fixed_steps(mut self, max_fixed_steps: u32) -> Self {
+        self.max_fixed_steps = max_fixed_steps;
+        self
+    }
+
+    /// Construct game with the statistics overlay shown or hidden.
+    ///
+    /// See [`Self::set_stats_overlay`].
+    #[must_use]
+    pub fn with_stats_overlay(mut self, stats_overlay: bool) -> Self {
+        self.stats_overlay = stats_overlay;
+        self
+    }
+
+    /// Show or hide the statistics overlay.
+    ///
+    /// The overlay is drawn in the top-left corner of the window, on top of every entity. The
+    /// first bar shows the frame time, followed by one bar per phase of [`Self::stats`], in phase
+    /// order. The gray line marks the frame time of 60 frames per second.
+    pub fn set_stats_overlay(&mut self, stats_overlay: bool) {
+        self.stats_overlay = stats_overlay;
+    }
+
+    /// Timing statistics of the last frame.
+    ///
+    /// See [`FrameStats`].
+    #[must_use]
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Push a scene on top of the scene stack.
+    ///
+    /// Unlike [`SceneControl::push`], the scene is pushed immediately.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the scene stack panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{
+    ///     entities, entity, error::CtraitResult, game::Game, scene::SceneEntities, traits::Update,
+    /// };
+    ///
+    /// struct Player;
+    /// impl Update for Player {
+    ///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let player = entity!(Player);
+    /// let mut level = SceneEntities::default();
+    /// level.update_entities.add_entities(&entities!(Update; player));
+    ///
+    /// let mut game = Game::new();
+    /// game.push_scene(level);
+    /// assert_eq!(game.scene_count(), 1);
+    /// ```
+    pub fn push_scene<S: Scene + 'static>(&mut self, scene: S) {
+        self.scenes.push(Box::new(scene));
+    }
+
+    /// Pop the top scene from the scene stack, returning it.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the scene stack panics.
+    pub fn pop_scene(&mut self) -> Option<Box<dyn Scene>> {
+        self.scenes.pop()
+    }
+
+    /// Replace the top scene of the scene stack with the given scene, returning the old scene.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the scene stack panics.
+    pub fn replace_scene<S: Scene + 'static>(&mut self, scene: S) -> Option<Box<dyn Scene>> {
+        let old = self.scenes.pop();
+        self.scenes.push(Box::new(scene));
+        old
+    }
+
+    /// Number of scenes in the scene stack.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the scene stack panics.
+    #[must_use]
+    pub fn scene_count(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// Returns a handle which can be used to request scene transitions, for example from inside
+    /// an entity's [`Update::update`].
+    ///
+    /// Requests are applied at the end of each frame, after all updates have run.
+    #[must_use]
+    pub fn scene_control(&self) -> SceneControl {
+        SceneControl::clone(&self.scene_control)
+    }
+
+    /// Returns a handle which can be used to control the game, for example to quit from inside
+    /// an entity's [`Update::update`].
+    ///
+    /// Requests are applied at the end of each frame, after all updates have run and before
+    /// rendering.
+    #[must_use]
+    pub fn control(&self) -> GameControl {
+        GameControl::clone(&self.control)
+    }
+
+    /// Pause or resume the game.
+    ///
+    /// While paused, neither [`Update::update`] nor [`FixedUpdate::fixed_update`] are called,
+    /// except on entities using unscaled time (see [`Update::unscaled_time`] and
+    /// [`FixedUpdate::unscaled_time`]). Events are still dispatched and entities are still
+    /// rendered.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.clock.set_paused(paused);
+    }
+
+    /// Returns `true` if the game is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    /// Set the time scale of the game.
+    ///
+    /// The `delta` passed to [`Update::update`] is multiplied by the time scale, and fixed steps
+    /// are run proportionally more or less often. A time scale of `0.5` results in slow motion,
+    /// while a time scale of `2.0` results in fast-forward. Negative values are treated as `0.0`.
+    /// Entities using unscaled time are not affected.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the time scale is infinite or NaN.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entities, error::CtraitResult, game::Game, traits::Update};
+    ///
+    /// struct Clock {
+    ///     elapsed: f32,
+    /// }
+    ///
+    /// impl Update for Clock {
+    ///     fn update(&mut self, delta: f32) -> CtraitResult<()> {
+    ///         self.elapsed += delta;
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let clock = entity!(Clock { elapsed: 0.0 });
+    /// let mut game = Game::new();
+    /// game.update_entities.add_entities(&entities!(Update; clock));
+    /// game.set_time_scale(0.5);
+    /// game.step(1.0);
+    /// assert_eq!(clock.read().unwrap().elapsed, 0.5);
+    /// ```
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        assert!(
+            time_scale.is_finite(),
+            "time scale must be finite, got {}",
+            time_scale
+        );
+        self.clock.set_time_scale(time_scale);
+    }
+
+    /// Returns the time scale of the game.
+    #[must_use]
+    pub fn time_scale(&self) -> f32 {
+        self.clock.time_scale()
+    }
+
+    /// Returns a handle which can be used to queue commands, such as spawning and despawning
+    /// entities from inside an entity's callbacks.
+    ///
+    /// Commands are applied at the end of each phase (see the [phase order](Self#phase-order)).
+    #[must_use]
+    pub fn commands(&self) -> Commands {
+        Commands::clone(&self.commands)
+    }
+
+    /// Returns a handle which can be used to schedule delayed and repeating callbacks.
+    ///
+    /// Timers are driven by the game's clock, see [`Scheduler`].
+    #[must_use]
+    pub fn scheduler(&self) -> Scheduler {
+        Scheduler::clone(&self.scheduler)
+    }
+
+    /// Returns a handle to the parent/child relationships between the game's entities.
+    ///
+    /// See [`Hierarchy`].
+    #[must_use]
+    pub fn hierarchy(&self) -> Hierarchy {
+        Hierarchy::clone(&self.hierarchy)
+    }
+
+    /// Returns a handle to the names and tags of the game's entities.
+    ///
+    /// See [`Registry`].
+    #[must_use]
+    pub fn registry(&self) -> Registry {
+        Registry::clone(&self.registry)
+    }
+
+    /// Returns the entity with the given name in the game's [`Registry`], if it exists and is of
+    /// type `T`.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the registry panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, game::Game};
+    ///
+    /// struct Player {
+    ///     lives: u32,
+    /// }
+    ///
+    /// let game = Game::new();
+    /// let player = entity!(Player { lives: 3 });
+    /// game.registry().set_name(&player, "player").unwrap();
+    ///
+    /// let player = game.find_by_name::<Player>("player").unwrap();
+    /// assert_eq!(player.read().unwrap().lives, 3);
+    /// ```
+    #[must_use]
+    pub fn find_by_name<T: Send + Sync + 'static>(&self, name: &str) -> Option<Entity<T>> {
+        self.registry.find_by_name(name)
+    }
+
+    /// Returns the entities of type `T` with the given tag in the game's [`Registry`].
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the registry panics.
+    #[must_use]
+    pub fn with_tag<T: Send + Sync + 'static>(&self, tag: &str) -> Vec<Entity<T>> {
+        self.registry.with_tag(tag)
+    }
+
+    /// Returns a handle to the bus of typed events exchanged between the game's entities.
+    ///
+    /// See [`EventBus`].
+    #[must_use]
+    pub fn event_bus(&self) -> EventBus {
+        EventBus::clone(&self.event_bus)
+    }
+
+    /// Queue the event on the game's [`EventBus`], to be delivered to its subscribers in this
+    /// frame or the next one.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the bus panics.
+    pub fn emit<E: Send + 'static>(&self, event: E) {
+        self.event_bus.emit(event);
+    }
+
+    /// Returns the world storing the game's component entities.
+    ///
+    /// See [`ecs`](crate::ecs).
+    #[must_use]
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Returns the world storing the game's component entities mutably.
+    ///
+    /// See [`ecs`](crate::ecs).
+    #[must_use]
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Add a system, run in the given phase with the game's [`World`] and the `delta` of the
+    /// phase.
+    ///
+    /// Systems are run after the entities of the phase, in the order they were added. Errors
+    /// returned by a system are reported like errors of entities, see
+    /// [`Self::with_error_handler`], and its panics are handled according to the game's
+    /// [`PanicPolicy`]. To spawn or despawn component entities from a trait entity,
+    /// queue a command modifying [`Self::world_mut`] through [`Self::commands`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{ecs::SystemPhase, game::Game};
+    ///
+    /// struct Lifetime(f32);
+    ///
+    /// let mut game = Game::new();
+    /// game.world_mut().spawn((Lifetime(1.5),));
+    /// // Despawn entities once their lifetime is over.
+    /// game.add_system(SystemPhase::Update, |world, delta| {
+    ///     let mut expired = Vec::new();
+    ///     world
+    ///         .query::<(&mut Lifetime,)>()
+    ///         .for_each_with_id(|id, (lifetime,)| {
+    ///             lifetime.0 -= delta;
+    ///             if lifetime.0 <= 0.0 {
+    ///                 expired.push(id);
+    ///             }
+    ///         });
+    ///     for id in expired {
+    ///         world.despawn(id);
+    ///     }
+    ///     Ok(())
+    /// });
+    ///
+    /// game.step(1.0);
+    /// assert_eq!(game.world().len(), 1);
+    /// game.step(1.0);
+    /// assert!(game.world().is_empty());
+    /// ```
+    pub fn add_system<F>(&mut self, phase: SystemPhase, system: F)
+    where
+        F: FnMut(&mut World, f32) -> CtraitResult<()> + Send + 'static,
+    {
+        self.systems.push(phase, system);
+    }
+
+    // Run the systems of the given phase, unless the phase has no scaled delta.
+    fn run_systems(&mut self, phase: SystemPhase, delta: Option<f32>) {
+        if let Some(delta) = delta {
+            let isolation = self.isolation();
+            self.systems.run(phase, &mut self.world, delta, &isolation);
+        }
+    }
+
+    /// Add the entity to the game's container for `T`, unless the container already holds it.
+    ///
+    /// Returns `true` if the entity was added. Entities of [`Self::start_entities`] are removed
+    /// once started, after which registering them again starts them again. The entity is
+    /// assigned an identifier if it has none, see [`Self::entity_ids`].
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the entity container panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entity::Entity, error::CtraitResult, game::Game, traits::Update};
+    ///
+    /// struct Player;
+    /// impl Update for Player {
+    ///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut game = Game::new();
+    /// let player: Entity<dyn Update> = entity!(Player);
+    /// assert!(game.register(&player));
+    /// // The player is not updated twice per frame.
+    /// assert!(!game.register(&player));
+    /// ```
+    pub fn register<T: ?Sized + Registrable>(&mut self, entity: &Entity<T>) -> bool {
+        self.ids.id(entity);
+        T::register(self, entity)
+    }
+
+    /// Register the entity in every container of the game whose trait it implements, and return
+    /// its identifier, which is assigned before the entity is registered.
+    ///
+    /// Which containers the entity is registered in is defined by its [`Spawn`] implementation,
+    /// usually generated with the [`spawnable`](crate::spawnable) macro. Containers already
+    /// holding the entity are skipped, so spawning an entity twice has no effect.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the entity containers panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{
+    ///     entity,
+    ///     error::CtraitResult,
+    ///     game::Game,
+    ///     spawnable,
+    ///     traits::{FixedUpdate, Update},
+    /// };
+    ///
+    /// struct Ball;
+    /// impl Update for Ball {
+    ///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    /// impl FixedUpdate for Ball {
+    ///     fn fixed_update(&mut self, _: f32) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    /// spawnable!(Ball: Update, FixedUpdate);
+    ///
+    /// let mut game = Game::new();
+    /// let ball = entity!(Ball);
+    /// let id = game.spawn(&ball);
+    /// assert_eq!(game.spawn(&ball), id);
+    /// assert_eq!(game.fixed_update_entities.len(), 1);
+    /// ```
+    pub fn spawn<T: Spawn>(&mut self, entity: &Entity<T>) -> EntityId {
+        let id = self.ids.id(entity);
+        T::spawn(entity, self);
+        id
+    }
+
+    /// Returns a handle to the registry assigning identifiers to the game's entities.
+    ///
+    /// Entities are assigned an identifier when they are spawned with [`Self::spawn`], registered
+    /// with [`Self::register`], or added to one of the game's containers, such as with
+    /// [`Entities::add_entities`]. Entities only held by the containers of a scene are assigned one
+    /// on the first call to [`EntityIds::id`]. See [`EntityIds`].
+    #[must_use]
+    pub fn entity_ids(&self) -> EntityIds {
+        EntityIds::clone(&self.ids)
+    }
+
+    /// Look up the entity with the given identifier in the containers of type `T` of the game
+    /// and of all scenes in the scene stack.
+    ///
+    /// Returns [`None`] if the identifier is stale or the entity is not in any such container.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the entity containers panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entities, error::CtraitResult, game::Game, traits::Update};
+    ///
+    /// struct Enemy;
+    /// impl Update for Enemy {
+    ///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut game = Game::new();
+    /// let enemy = entity!(Enemy);
+    /// game.update_entities.add_entities(&entities!(Update; enemy));
+    /// let id = game.entity_ids().get(&enemy).unwrap();
+    ///
+    /// assert!(game.lookup::<dyn Update>(id).is_some());
+    /// ```
+    #[must_use]
+    pub fn lookup<T: ?Sized + EntityTrait>(&self, id: EntityId) -> Option<Entity<T>> {
+        let address = self.ids.address(id)?;
+        T::game_entities(self).find_address(address).or_else(|| {
+            self.scenes
+                .entities()
+                .iter()
+                .find_map(|entities| T::scene_entities(entities).find_address(address))
+        })
+    }
+
+    /// Remove the given entity from every entity container of the game and of all scenes in the
+    /// scene stack.
+    ///
+    /// If the entity is part of [`Self::destroy_entities`], [`Destroy::destroy`] is called. The
+    /// entity loses its name and tags in the [`Registry`], and its children in the [`Hierarchy`]
+    /// are despawned as well.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the entity containers panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entities, error::CtraitResult, game::Game, traits::Update};
+    ///
+    /// struct Enemy;
+    /// impl Update for Enemy {
+    ///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let enemy = entity!(Enemy);
+    /// let mut game = Game::new();
+    /// game.update_entities.add_entities(&entities!(Update; enemy));
+    /// game.despawn(&enemy);
+    /// ```
+    pub fn despawn<T: ?Sized>(&mut self, entity: &Entity<T>) {
+        let address = entity::address(entity);
+        self.registry.remove_address(address);
+        for child in self.hierarchy.remove_address(address) {
+            self.despawn(&child);
+        }
+        self.ids.remove_address(address);
+        self.pre_update_entities.remove_address(address);
+        self.update_entities.remove_address(address);
+        self.late_update_entities.remove_address(address);
+        self.fixed_update_entities.remove_address(address);
+        self.renderable_entities.remove_address(address);
+        self.interactive_entities.remove_address(address);
+        self.start_entities.remove_address(address);
+        for entities in self.scenes.entities() {
+            entities.remove_address(address);
+        }
+        let isolation = self.isolation();
+        for destroyed in self.destroy_entities.remove_address(address) {
+            isolation.call(&destroyed, Destroy::destroy);
+        }
+    }
+
+    // Isolation of entity panics according to the game's policy.
+    fn isolation(&self) -> Isolation {
+        Isolation {
+            policy: self.panic_policy,
+            commands: Commands::clone(&self.commands),
+        }
+    }
+
+    // Apply all queued commands.
+    fn apply_commands(&mut self) {
+        for command in self.commands.take() {
+            command(self);
+        }
+    }
+
+    /// Take the first error returned by an entity which was not passed to an error handler.
+    ///
+    /// This is useful to check for errors when driving the game with [`Self::step`]. See
+    /// [`Self::with_error_handler`].
+    pub fn take_error(&mut self) -> Option<EntityError> {
+        self.error.take()
+    }
+
+    // Pass the error to the error handler, or keep it if there is none.
+    pub(crate) fn report_error(&mut self, error: EntityError) {
+        if let Some(handler) = &mut self.error_handler {
+            handler(error);
+        } else if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    /// Returns the reason the game should stop, if any.
+    ///
+    /// This is useful to check if quit was requested when driving the game with [`Self::step`].
+    #[must_use]
+    pub fn exit_reason(&self) -> Option<ExitReason> {
+        self.exit
+    }
+
+    // Apply all pending requests of the control handle.
+    fn apply_control(&mut self) {
+        let requests = self.control.take();
+        if requests.exit.is_some() {
+            self.exit = requests.exit;
+        }
+        if let Some(paused) = requests.paused {
+            self.set_paused(paused);
+        }
+        if let Some(time_scale) = requests.time_scale {
+            self.set_time_scale(time_scale);
+        }
+        if let Some(timestep) = requests.timestep {
+            self.timestep = timestep;
+        }
+        // The window is only available while the game is started.
+        self.toggle_fullscreen ^= requests.toggle_fullscreen;
+    }
+
+    // Timestep in seconds.
+    fn timestep_secs(&self) -> f32 {
+        self.timestep as f32 / 1000.0
+    }
+
+    // Add delta to the accumulators and run all fixed steps that are due.
+    // Returns the number of scaled steps run.
+    fn accumulate(&mut self, delta: f32) -> u32 {
+        let timestep = self.timestep_secs();
+        self.accumulator += self.clock.scale(delta);
+        self.unscaled_accumulator += delta;
+        let steps = due_steps(&mut self.accumulator, timestep, self.max_fixed_steps);
+        let unscaled_steps = due_steps(
+            &mut self.unscaled_accumulator,
+            timestep,
+            self.max_fixed_steps,
+        );
+        for _ in 0..steps {
+            self.fixed_update(Deltas {
+                scaled: Some(timestep),
+                unscaled: None,
+            });
+        }
+        for _ in 0..unscaled_steps {
+            self.fixed_update(Deltas {
+                scaled: None,
+                unscaled: Some(timestep),
+            });
+        }
+        steps
+    }
+
+    // Step the fixed update systems when fixed updates of entities run on the timer thread, as
+    // the world is not shared with it.
+    fn step_fixed_systems(&mut self, delta: f32) {
+        let timestep = self.timestep_secs();
+        self.accumulator += self.clock.scale(delta);
+        for _ in 0..due_steps(&mut self.accumulator, timestep, self.max_fixed_steps) {
+            self.run_systems(SystemPhase::FixedUpdate, Some(timestep));
+            self.apply_commands();
+        }
+    }
+
+    // Call FixedUpdate::fixed_update on the game's and the active scenes' entities.
+    fn fixed_update(&mut self, deltas: Deltas) {
+        fixed_update(
+            &self.isolation(),
+            &containers(&self.fixed_update_entities, &self.scenes, |entities| {
+                &entities.fixed_update_entities
+            }),
+            deltas,
+            self.parallel,
+        );
+        self.run_systems(SystemPhase::FixedUpdate, deltas.scaled);
+        self.apply_commands();
+    }
+
+    // Renderable containers of the game and of all rendered scenes.
+    fn renderable_containers(&self) -> Vec<Entities<dyn Renderable>> {
+        let mut renderable_entities = vec![Entities::clone(&self.renderable_entities)];
+        renderable_entities.extend(
+            self.scenes
+                .active(ScenePhase::Render, |entities| &entities.renderable_entities),
+        );
+        renderable_entities
+    }
+
+    // Count the entities of the game's and the active scenes' containers.
+    fn count_entities(&mut self) {
+        fn count<T: ?Sized>(containers: &[Entities<T>]) -> usize {
+            containers
+                .iter()
+                .map(|entities| {
+                    entities
+                        .access()
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .len()
+                })
+                .sum()
+        }
+        self.stats.entity_counts = EntityCounts {
+            pre_update: count(&containers(
+                &self.pre_update_entities,
+                &self.scenes,
+                |entities| &entities.pre_update_entities,
+            )),
+            update: count(&containers(
+                &self.update_entities,
+                &self.scenes,
+                |entities| &entities.update_entities,
+            )),
+            late_update: count(&containers(
+                &self.late_update_entities,
+                &self.scenes,
+                |entities| &entities.late_update_entities,
+            )),
+            fixed_update: count(&containers(
+                &self.fixed_update_entities,
+                &self.scenes,
+                |entities| &entities.fixed_update_entities,
+            )),
+            renderable: count(&self.renderable_containers()),
+            interactive: count(&containers(
+                &self.interactive_entities,
+                &self.scenes,
+                |entities| &entities.interactive_entities,
+            )),
+        };
+    }
+
+    // Fraction of a fixed step remaining in the accumulator.
+    fn alpha(&self) -> f32 {
+        match self.fixed_update_mode {
+            FixedUpdateMode::Accumulated => self.accumulator / self.timestep_secs(),
+            FixedUpdateMode::Threaded => 1.0,
+        }
+    }
+
+    /// Advance the game by `delta` seconds without a window.
+    ///
+    /// This dispatches to the entity containers exactly as a single iteration of the loop in
+    /// [`Self::start`] does (see the [phase order](Self#phase-order)): released entities of
+    /// [`Self::destroy_entities`] are destroyed, then pre update entities are called, then with
+    /// [`FixedUpdateMode::Accumulated`], all fixed steps that are due are run, followed by due
+    /// timers of [`Self::scheduler`], and the update and late update entities. With
+    /// [`FixedUpdateMode::Threaded`], fixed steps are left to the timer thread. The game's pause
+    /// state and time scale are taken into account.
+    /// Afterwards, pending scene transitions requested through [`Self::scene_control`] and
+    /// requests made through [`Self::control`] are applied. Events are not polled; use
+    /// [`Self::inject_event`] to simulate them. As there is no render context,
+    /// [`Self::start_entities`] are not started.
+    ///
+    /// Returns the number of fixed steps that were run according to the game's time scale, as
+    /// counted by [`FrameStats::fixed_steps`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if another user of the entity containers panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{entity, entities, error::CtraitResult, game::Game, traits::FixedUpdate};
+    ///
+    /// struct Falling {
+    ///     y: f32,
+    /// }
+    ///
+    /// impl FixedUpdate for Falling {
+    ///     fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
+    ///         self.y += 10.0 * delta;
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let falling = entity!(Falling { y: 0.0 });
+    /// let mut game = Game::new().with_timestep(100);
+    /// game.fixed_update_entities
+    ///     .add_entities(&entities!(FixedUpdate; falling));
+    ///
+    /// // Simulate one second of game time.
+    /// for _ in 0..10 {
+    ///     game.step(0.1);
+    /// }
+    /// assert!((falling.read().unwrap().y - 10.0).abs() < 0.1);
+    /// ```
+    pub fn step(&mut self, delta: f32) -> u32 {
+        let isolation = self.isolation();
+        // An infinite delta is recorded as the longest possible frame.
+        self.stats.record_frame(
+            time::Duration::try_from_secs_f32(delta.max(0.0)).unwrap_or(time::Duration::MAX),
+        );
+        let mut instant = Instant::now();
+        self.destroy_entities
+            .release()
+            .into_iter()
+            .for_each(|entity| isolation.call(&entity, Destroy::destroy));
+        self.apply_commands();
+        self.stats.phases.destroy = lap(&mut instant);
+        let deltas = self.clock.deltas(delta);
+        dispatch(
+            &self.isolation(),
+            &containers(&self.pre_update_entities, &self.scenes, |entities| {
+                &entities.pre_update_entities
+            }),
+            |entity| {
+                deltas
+                    .get(entity.unscaled_time())
+                    .filter(|_| entity.pre_update_active())
+                    .map_or(Ok(()), |delta| entity.pre_update(delta))
+            },
+        );
+        self.run_systems(SystemPhase::PreUpdate, deltas.scaled);
+        self.apply_commands();
+        self.stats.phases.pre_update = lap(&mut instant);
+        let steps = match self.fixed_update_mode {
+            FixedUpdateMode::Accumulated => self.accumulate(delta),
+            FixedUpdateMode::Threaded => {
+                self.step_fixed_systems(delta);
+                0
+            }
+        };
+        self.stats.fixed_steps = steps;
+        self.stats.phases.fixed_update = lap(&mut instant);
+        Scheduler::clone(&self.scheduler).run(self, deltas.scaled, deltas.unscaled);
+        self.apply_commands();
+        self.stats.phases.timers = lap(&mut instant);
+        dispatch_update(
+            &self.isolation(),
+            &containers(&self.update_entities, &self.scenes, |entities| {
+                &entities.update_entities
+            }),
+            self.parallel,
+            |entity| entity.main_thread(),
+            |entity| {
+                deltas
+                    .get(entity.unscaled_time())
+                    .filter(|_| entity.update_active())
+                    .map_or(Ok(()), |delta| entity.update(delta))
+            },
+        );
+        self.run_systems(SystemPhase::Update, deltas.scaled);
+        self.apply_commands();
+        self.stats.phases.update = lap(&mut instant);
+        dispatch(
+            &self.isolation(),
+            &containers(&self.late_update_entities, &self.scenes, |entities| {
+                &entities.late_update_entities
+            }),
+            |entity| {
+                deltas
+                    .get(entity.unscaled_time())
+                    .filter(|_| entity.late_update_active())
+                    .map_or(Ok(()), |delta| entity.late_update(delta))
+            },
+        );
+        self.run_systems(SystemPhase::LateUpdate, deltas.scaled);
+        self.apply_commands();
+        self.hierarchy.propagate(&self.isolation());
+        self.event_bus.dispatch(&self.isolation());
+        self.apply_commands();
+        self.stats.phases.late_update = lap(&mut instant);
+        self.scenes.apply(&self.scene_control);
+        self.apply_control();
+        self.count_entities();
+        steps
+    }
+
+    /// Run a single fixed step on all fixed update entities.
+    ///
+    /// The `delta` passed to [`FixedUpdate::fixed_update`] is equal to the game's timestep.
+    /// The step is run regardless of the game's pause state and time scale. The accumulator used
+    /// by [`Self::step`] is left untouched.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if another user of the entity containers panics.
+    pub fn fixed_step(&mut self) {
+        let timestep = self.timestep_secs();
+        self.fixed_update(Deltas {
+            scaled: Some(timestep),
+            unscaled: Some(timestep),
+        });
+    }
+
+    /// Dispatch an event to all interactive entities, as if it was polled from the window.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if another user of the entity containers panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{
+    ///     entities, entity, error::CtraitResult, game::Game, traits::Interactive, Event, Keycode,
+    /// };
+    ///
+    /// #[derive(Default)]
+    /// struct Jumper {
+    ///     jumped: bool,
+    /// }
+    ///
+    /// impl Interactive for Jumper {
+    ///     fn on_event(&mut self, event: &Event) -> CtraitResult<()> {
+    ///         if let Event::KeyDown { keycode: Some(Keycode::Space), .. } = event {
+    ///             self.jumped = true;
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let jumper = entity!(Jumper::default());
+    /// let mut game = Game::new();
+    /// game.interactive_entities
+    ///     .add_entities(&entities!(Interactive; jumper));
+    /// game.inject_event(Event::KeyDown {
+    ///     timestamp: 0,
+    ///     window_id: 0,
+    ///     keycode: Some(Keycode::Space),
+    ///     scancode: None,
+    ///     keymod: ctrait::keyboard::Mod::NOMOD,
+    ///     repeat: false,
+    /// });
+    /// assert!(jumper.read().unwrap().jumped);
+    /// ```
+    pub fn inject_event(&mut self, event: Event) {
+        dispatch(
+            &self.isolation(),
+            &containers(&self.interactive_entities, &self.scenes, |entities| {
+                &entities.interactive_entities
+            }),
+            |entity| {
+                if entity.event_active() {
+                    entity.on_event(&event)
+                } else {
+                    Ok(())
+                }
+            },
+        );
+        self.apply_commands();
+    }
+
+    // Run fixed updates on a separate timer thread.
+    fn schedule_fixed_update(&self, timer: &Timer) -> Guard {
+        let mut fixed_update_instant = Instant::now();
+        let fixed_update_entities = Entities::clone(&self.fixed_update_entities);
+        let scenes = SceneStack::clone(&self.scenes);
+        let clock = Clock::clone(&self.clock);
+        let parallel = self.parallel;
+        let isolation = self.isolation();
+        timer.schedule_repeating(Duration::milliseconds(self.timestep), move || {
+            fixed_update(
+                &isolation,
+                &containers(&fixed_update_entities, &scenes, |entities| {
+                    &entities.fixed_update_entities
+                }),
+                clock.deltas(fixed_update_instant.elapsed().as_secs_f32()),
+                parallel,
+            );
+            fixed_update_instant = Instant::now();
+        })
+    }
+
+    /// Start the game with the given renderer.
+    ///
+    /// This will block until the window is closed or quit is requested through
+    /// [`Self::control`]. Returns the reason the game stopped.
+    ///
+    /// The frame rate is limited according to
+    /// [`RendererConfig::target_fps`](crate::graphics::RendererConfig::target_fps) and
+    /// [`RendererConfig::background_fps`](crate::graphics::RendererConfig::background_fps).
+    ///
+    /// # Errors
+    ///
+    /// If [`sdl2`] fails to start, a [`CtraitError`](crate::error::CtraitError) variant will be
+    /// returned.
+    ///
+    /// If an entity returns an error and no error handler was set with
+    /// [`Self::with_error_handler`], [`CtraitError::Entity`](crate::error::CtraitError::Entity)
+    /// is returned.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if another user of the entity containers panics.
+    pub fn start(&mut self, renderer: &mut Renderer) -> CtraitResult<ExitReason> {
+        let sdl_context = sdl2::init()?;
+        let mut event_pump = sdl_context.event_pump()?;
+        let video_subsystem = sdl_context.video()?;
+        let canvas = renderer.config.create_canvas(&video_subsystem)?;
+        let texture_creator = canvas.texture_creator();
+        let texture_manager = TextureManager::new(&texture_creator);
+        let mut render_context = RenderContext::new(canvas, texture_manager);
+        // Start fixed update processs if requested.
+        let timer = Timer::new();
+        let mut scheduled_timestep = self.timestep;
+        let mut guard = match self.fixed_update_mode {
+            FixedUpdateMode::Accumulated => None,
+            FixedUpdateMode::Threaded => Some(self.schedule_fixed_update(&timer)),
+        };
+        self.accumulator = 0.0;
+        self.unscaled_accumulator = 0.0;
+        self.exit = None;
+        self.toggle_fullscreen = false;
+        // Start standard game loop.
+        let mut pacer = FramePacer::new();
+        let mut standard_instant = Instant::now();
+        loop {
+            let mut instant = Instant::now();
+            for event in event_pump.poll_iter() {
+                renderer.process_event(&event);
+                if renderer.has_quit() {
+                    break;
+                }
+                self.inject_event(event);
+            }
+            let events = lap(&mut instant);
+            start(&self.isolation(), &self.start_entities, &mut render_context);
+            self.apply_commands();
+            let start_time = lap(&mut instant);
+            let delta = standard_instant.elapsed().as_secs_f32();
+            standard_instant = Instant::now();
+            self.step(delta);
+            self.stats.phases.events = events;
+            self.stats.phases.start = start_time;
+            if renderer.has_quit() {
+                return Ok(ExitReason::WindowClosed);
+            }
+            if let Some(error) = self.error.take() {
+                return Err(error.into());
+            }
+            if let Some(exit) = self.exit {
+                return Ok(exit);
+            }
+            if guard.is_some() && scheduled_timestep != self.timestep {
+                // Reschedule the fixed update thread with the new timestep.
+                scheduled_timestep = self.timestep;
+                guard = Some(self.schedule_fixed_update(&timer));
+            }
+            if self.toggle_fullscreen {
+                self.toggle_fullscreen = false;
+                let window = render_context.canvas.window_mut();
+                let fullscreen = match window.fullscreen_state() {
+                    FullscreenType::Off => FullscreenType::Desktop,
+                    _ => FullscreenType::Off,
+                };
+                window.set_fullscreen(fullscreen)?;
+            }
+            render_context.set_alpha(self.alpha());
+            let renderable_entities = self.renderable_containers();
+            let mut instant = Instant::now();
+            self.stats.render_times = renderer.render(
+                &mut render_context,
+                &renderable_entities,
+                self.stats_overlay.then_some(&self.stats),
+                &self.isolation(),
+            );
+            self.stats.phases.render = lap(&mut instant);
+            pacer.wait(renderer.target_fps());
+        }
+    }
+}
+
+// The given root container followed by the selected container of every updated scene.
+fn containers<T: ?Sized>(
+    root: &Entities<T>,
+    scenes: &SceneStack,
+    select: fn(&SceneEntities) -> &Entities<T>,
+) -> Vec<Entities<T>> {
+    let mut containers = vec![Entities::clone(root)];
+    containers.extend(scenes.active(ScenePhase::Update, select));
+    containers
+}
+
+// Call Start::start on every entity in the container, removing them from the container.
+fn start(isolation: &Isolation, entities: &Entities<dyn Start>, context: &mut RenderContext) {
+    // The container is released before the entities are started, so they can register new
+    // entities to be started.
+    let started: Vec<_> = entities
+        .access()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .drain(..)
+        .collect();
+    started
+        .iter()
+        .filter_map(std::sync::Weak::upgrade)
+        .for_each(|entity| isolation.call(&entity, |entity| entity.start(context)));
+}
+
+// Remove all whole steps from the accumulator, up to the given maximum.
+// Returns the number of steps removed.
+fn due_steps(accumulator: &mut f32, timestep: f32, max_steps: u32) -> u32 {
+    let mut steps = 0;
+    while *accumulator >= timestep {
+        if steps == max_steps {
+            // Spiral of death: drop the whole steps that could not be run.
+            *accumulator %= timestep;
+            break;
+        }
+        *accumulator -= timestep;
+        steps += 1;
+    }
+    // An infinite delta leaves the accumulator infinite or NaN, which would never compare
+    // greater than the timestep again.
+    if !accumulator.is_finite() {
+        *accumulator = 0.0;
+    }
+    steps
+}
+
+// Call the given function on every entity in the containers, in order.
+fn dispatch<T: ?Sized + Dispatched + Send + Sync + 'static>(
+    isolation: &Isolation,
+    containers: &[Entities<T>],
+    mut call: impl FnMut(&mut T) -> CtraitResult<()>,
+) {
+    for entities in containers {
+        entities
+            .access()
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .for_each(|entity| isolation.call(&entity.upgrade().unwrap(), &mut call));
+    }
+}
+
+// Like dispatch, but spreads the entities of each container over the thread pool if parallel is
+// true. Containers marked as main thread are called serially, and entities for which main_thread
+// returns true are called serially after the other entities of their container.
+fn dispatch_update<T: ?Sized + Dispatched + Send + Sync + 'static>(
+    isolation: &Isolation,
+    containers: &[Entities<T>],
+    parallel: bool,
+    main_thread: fn(&T) -> bool,
+    call: impl Fn(&mut T) -> CtraitResult<()> + Sync,
+) {
+    #[cfg(feature = "parallel")]
+    if parallel {
+        use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+        for entities in containers {
+            if entities.is_main_thread() {
+                dispatch(isolation, std::slice::from_ref(entities), &call);
+                continue;
+            }
+            // The container is released before the entities are updated, as they are locked
+            // from other threads.
+            let parallel: Vec<_> = entities
+                .access()
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .filter_map(std::sync::Weak::upgrade)
+                .collect();
+            // Entities are checked while locked for their call, rather than locked once more
+            // beforehand.
+            let serial = std::sync::Mutex::new(Vec::new());
+            parallel.par_iter().enumerate().for_each(|(index, entity)| {
+                isolation.call(entity, |locked| {
+                    if main_thread(locked) {
+                        serial
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .push(index);
+                        Ok(())
+                    } else {
+                        call(locked)
+                    }
+                });
+            });
+            let mut serial = serial.into_inner().unwrap_or_else(PoisonError::into_inner);
+            serial.sort_unstable();
+            serial
+                .iter()
+                .for_each(|&index| isolation.call(&parallel[index], &call));
+        }
+        return;
+    }
+    #[cfg(not(feature = "parallel"))]
+    let _ = (parallel, main_thread);
+    dispatch(isolation, containers, call);
+}
+
+// Call FixedUpdate::fixed_update on every entity in the containers.
+fn fixed_update(
+    isolation: &Isolation,
+    containers: &[Entities<dyn FixedUpdate>],
+    deltas: Deltas,
+    parallel: bool,
+) {
+    dispatch_update(
+        isolation,
+        containers,
+        parallel,
+        |entity| entity.main_thread(),
+        |entity| {
+            deltas
+                .get(entity.unscaled_time())
+                .filter(|_| entity.fixed_update_active())
+                .map_or(Ok(()), |delta| entity.fixed_update(delta))
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CtraitResult, Event, ExitReason, FixedUpdate, FixedUpdateMode, Game, Interactive,
+        LateUpdate, PanicPolicy, PreUpdate, SystemPhase, Update,
+    };
+    use crate::{
+        bus::EventBus,
+        command::Commands,
+        entity::{self, Entities, Entity},
+        error::CtraitError,
+        math::Vector2,
+        scene::SceneEntities,
+        traits::{Destroy, Spatial},
+        transform::Transform,
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    #[test]
+    fn game_send() {
+        // A game can be moved to another thread, so every stored callback must be Send.
+        fn assert_send<T: Send>() {}
+        assert_send::<Game>();
+    }
+
+    #[test]
+    fn game_default() {
+        let game = Game::default();
+        // Ensure, by default, there are no entities in any of the entity containers.
+        assert!(game.update_entities.access().lock().unwrap().is_empty());
+        assert!(game
+            .fixed_update_entities
+            .access()
+            .lock()
+            .unwrap()
+            .is_empty());
+        assert!(game.renderable_entities.access().lock().unwrap().is_empty());
+        assert!(game
+            .interactive_entities
+            .access()
+            .lock()
+            .unwrap()
+            .is_empty());
+        // Timestep should be default.
+        assert_eq!(game.timestep, Game::DEFAULT_TIMESTEP);
+        assert_eq!(game.fixed_update_mode, FixedUpdateMode::Accumulated);
+        assert_eq!(game.max_fixed_steps, Game::DEFAULT_MAX_FIXED_STEPS);
+    }
+
+    #[test]
+    fn game_with_timestep() {
+        let game = Game::default().with_timestep(12);
+        assert_eq!(game.timestep, 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "timestep must be positive")]
+    fn game_with_negative_timestep() {
+        let _ = Game::default().with_timestep(-10);
+    }
+
+    #[test]
+    fn game_with_fixed_update_mode() {
+        let game = Game::default().with_fixed_update_mode(FixedUpdateMode::Threaded);
+        assert_eq!(game.fixed_update_mode, FixedUpdateMode::Threaded);
+    }
+
+    #[test]
+    fn game_accumulate() {
+        struct Counter(u32);
+        impl FixedUpdate for Counter {
+            fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
+                assert!((delta - 0.01).abs() < f32::EPSILON);
+                self.0 += 1;
+                Ok(())
+            }
+        }
+        let counter = crate::entity!(Counter(0));
+        let mut game = Game::default().with_timestep(10);
+        game.fixed_update_entities
+            .add_entities(&[Entity::clone(&counter) as Entity<dyn FixedUpdate>]);
+        // Not enough time has passed for a single step.
+        assert_eq!(game.accumulate(0.005), 0);
+        assert!((game.alpha() - 0.5).abs() < 1e-4);
+        // Carried over time is used in the next frame.
+        assert_eq!(game.accumulate(0.016), 2);
+        assert_eq!(counter.read().unwrap().0, 2);
+        assert!((game.alpha() - 0.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn game_accumulate_infinite() {
+        let mut game = Game::default().with_timestep(10).with_max_fixed_steps(3);
+        assert_eq!(game.accumulate(f32::INFINITY), 3);
+        assert_eq!(game.accumulator, 0.0);
+        // Fixed steps keep running afterwards.
+        assert_eq!(game.accumulate(0.015), 1);
+    }
+
+    #[test]
+    fn game_step_infinite() {
+        let mut game = Game::default().with_max_fixed_steps(3);
+        assert_eq!(game.step(f32::INFINITY), 3);
+        assert_eq!(game.stats().frame_time, std::time::Duration::MAX);
+        game.step(f32::NAN);
+        assert_eq!(game.stats().frame_time, std::time::Duration::ZERO);
+    }
+
+    #[test]
+    #[should_panic(expected = "time scale must be finite")]
+    fn game_infinite_time_scale() {
+        Game::default().set_time_scale(f32::INFINITY);
+    }
+
+    #[test]
+    fn game_accumulate_max_fixed_steps() {
+        let mut game = Game::default().with_timestep(10).with_max_fixed_steps(3);
+        assert_eq!(game.accumulate(1.005), 3);
+        // Excess steps are discarded, only the fraction of a step remains.
+        assert!(game.accumulator < game.timestep_secs());
+    }
+
+    #[test]
+    fn game_phase_order() {
+        #[derive(Default)]
+        struct Phases(Vec<&'static str>);
+        impl PreUpdate for Phases {
+            fn pre_update(&mut self, _: f32) -> CtraitResult<()> {
+                self.0.push("pre_update");
+                Ok(())
+            }
+        }
+        impl FixedUpdate for Phases {
+            fn fixed_update(&mut self, _: f32) -> CtraitResult<()> {
+                self.0.push("fixed_update");
+                Ok(())
+            }
+        }
+        impl Update for Phases {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                self.0.push("update");
+                Ok(())
+            }
+        }
+        impl LateUpdate for Phases {
+            fn late_update(&mut self, _: f32) -> CtraitResult<()> {
+                self.0.push("late_update");
+                Ok(())
+            }
+        }
+        let phases = crate::entity!(Phases::default());
+        let mut game = Game::default().with_timestep(10);
+        game.late_update_entities
+            .add_entities(&[Entity::clone(&phases) as Entity<dyn LateUpdate>]);
+        game.update_entities
+            .add_entities(&[Entity::clone(&phases) as Entity<dyn Update>]);
+        game.fixed_update_entities
+            .add_entities(&[Entity::clone(&phases) as Entity<dyn FixedUpdate>]);
+        game.pre_update_entities
+            .add_entities(&[Entity::clone(&phases) as Entity<dyn PreUpdate>]);
+        game.step(0.01);
+        assert_eq!(
+            phases.read().unwrap().0,
+            vec!["pre_update", "fixed_update", "update", "late_update"]
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        updates: Vec<f32>,
+        fixed_updates: Vec<f32>,
+        events: usize,
+    }
+
+    impl Update for Recorder {
+        fn update(&mut self, delta: f32) -> CtraitResult<()> {
+            self.updates.push(delta);
+            Ok(())
+        }
+    }
+
+    impl FixedUpdate for Recorder {
+        fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
+            self.fixed_updates.push(delta);
+            Ok(())
+        }
+    }
+
+    impl Interactive for Recorder {
+        fn on_event(&mut self, _: &Event) -> CtraitResult<()> {
+            self.events += 1;
+            Ok(())
+        }
+    }
+
+    fn recorder_game(mode: FixedUpdateMode) -> (Game, Entity<Recorder>) {
+        let recorder = crate::entity!(Recorder::default());
+        let mut game = Game::default()
+            .with_timestep(10)
+            .with_fixed_update_mode(mode);
+        game.update_entities
+            .add_entities(&[Entity::clone(&recorder) as Entity<dyn Update>]);
+        game.fixed_update_entities
+            .add_entities(&[Entity::clone(&recorder) as Entity<dyn FixedUpdate>]);
+        game.interactive_entities
+            .add_entities(&[Entity::clone(&recorder) as Entity<dyn Interactive>]);
+        (game, recorder)
+    }
+
+    #[test]
+    fn game_step() {
+        let (mut game, recorder) = recorder_game(FixedUpdateMode::Accumulated);
+        assert_eq!(game.step(0.025), 2);
+        let recorder = recorder.read().unwrap();
+        assert_eq!(recorder.updates, vec![0.025]);
+        assert_eq!(recorder.fixed_updates, vec![0.01, 0.01]);
+    }
+
+    #[test]
+    fn game_step_threaded() {
+        let (mut game, recorder) = recorder_game(FixedUpdateMode::Threaded);
+        // Fixed updates are left to the timer thread.
+        assert_eq!(game.step(0.025), 0);
+        let recorder = recorder.read().unwrap();
+        assert_eq!(recorder.updates, vec![0.025]);
+        assert!(recorder.fixed_updates.is_empty());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn game_parallel_updates() {
+        struct Agent {
+            updates: u32,
+            main_thread: bool,
+            thread: Option<std::thread::ThreadId>,
+        }
+        impl Update for Agent {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                self.updates += 1;
+                self.thread = Some(std::thread::current().id());
+                Ok(())
+            }
+
+            fn main_thread(&self) -> bool {
+                self.main_thread
+            }
+        }
+        let agents: Vec<_> = (0..100)
+            .map(|i| {
+                crate::entity!(Agent {
+                    updates: 0,
+                    main_thread: i == 0,
+                    thread: None,
+                })
+            })
+            .collect();
+        let mut game = Game::default().with_parallel_updates(true);
+        for agent in &agents {
+            game.update_entities
+                .add_entities(&[Entity::clone(agent) as Entity<dyn Update>]);
+        }
+        game.step(0.1);
+        assert!(agents
+            .iter()
+            .all(|agent| agent.read().unwrap().updates == 1));
+        assert_eq!(
+            agents[0].read().unwrap().thread,
+            Some(std::thread::current().id())
+        );
+
+        // Every entity of a main thread container is updated on the calling thread.
+        game.update_entities.set_main_thread(true);
+        game.step(0.1);
+        assert!(agents.iter().all(|agent| {
+            let agent = agent.read().unwrap();
+            agent.updates == 2 && agent.thread == Some(std::thread::current().id())
+        }));
+    }
+
+    #[test]
+    fn game_panic_policy() {
+        struct Faulty(u32);
+        impl Update for Faulty {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                self.0 += 1;
+                panic!("faulty update");
+            }
+        }
+        let skipped = crate::entity!(Faulty(0));
+        let removed = crate::entity!(Faulty(0));
+        let mut skip_game = Game::default().with_panic_policy(PanicPolicy::Skip);
+        skip_game
+            .update_entities
+            .add_entities(&[Entity::clone(&skipped) as Entity<dyn Update>]);
+        let mut remove_game = Game::default().with_panic_policy(PanicPolicy::Remove);
+        remove_game
+            .update_entities
+            .add_entities(&[Entity::clone(&removed) as Entity<dyn Update>]);
+        for _ in 0..2 {
+            skip_game.step(0.1);
+            remove_game.step(0.1);
+        }
+        assert_eq!(skipped.read().unwrap().0, 2);
+        assert_eq!(removed.read().unwrap().0, 1);
+        assert!(remove_game
+            .update_entities
+            .access()
+            .lock()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn game_errors() {
+        struct Failing;
+        impl Update for Failing {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Err(CtraitError::Other(String::from("update failed")))
+            }
+        }
+        let failing = crate::entity!(Failing);
+        let mut game = Game::default();
+        game.update_entities
+            .add_entities(&[Entity::clone(&failing) as Entity<dyn Update>]);
+        game.step(0.1);
+        game.step(0.1);
+        // Only the first error is kept.
+        let error = game.take_error().unwrap();
+        assert_eq!(error.phase, "update");
+        assert!(error.type_name.ends_with("Failing"));
+        assert!(game.take_error().is_none());
+        let errors = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&errors);
+        let mut game = Game::default().with_error_handler(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        game.update_entities
+            .add_entities(&[Entity::clone(&failing) as Entity<dyn Update>]);
+        game.step(0.1);
+        game.step(0.1);
+        assert_eq!(errors.load(Ordering::SeqCst), 2);
+        assert!(game.take_error().is_none());
+    }
+
+    #[test]
+    fn game_fixed_step() {
+        let (mut game, recorder) = recorder_game(FixedUpdateMode::Accumulated);
+        game.fixed_step();
+        let recorder = recorder.read().unwrap();
+        assert!(recorder.updates.is_empty());
+        assert_eq!(recorder.fixed_updates, vec![0.01]);
+    }
+
+    #[test]
+    fn game_stats() {
+        let (mut game, _recorder) = recorder_game(FixedUpdateMode::Accumulated);
+        let mut scene = SceneEntities::default();
+        let scene_recorder = crate::entity!(Recorder::default());
+        scene
+            .update_entities
+            .add_entities(&[Entity::clone(&scene_recorder) as Entity<dyn Update>]);
+        game.push_scene(scene);
+        game.step(0.025);
+        let stats = game.stats();
+        assert_eq!(stats.fixed_steps, 2);
+        assert_eq!(stats.frame_time, std::time::Duration::from_millis(25));
+        assert!((stats.fps - 40.0).abs() < 0.01);
+        // Entities of the active scene are counted as well.
+        assert_eq!(stats.entity_counts.update, 2);
+        assert_eq!(stats.entity_counts.fixed_update, 1);
+        assert_eq!(stats.entity_counts.renderable, 0);
+    }
+
+    #[test]
+    fn game_scenes() {
+        let (mut game, recorder) = recorder_game(FixedUpdateMode::Accumulated);
+        let scene_recorder = crate::entity!(Recorder::default());
+        let mut scene = SceneEntities::default();
+        scene
+            .update_entities
+            .add_entities(&[Entity::clone(&scene_recorder) as Entity<dyn Update>]);
+        game.push_scene(scene);
+        game.step(0.0);
+        assert_eq!(recorder.read().unwrap().updates.len(), 1);
+        assert_eq!(scene_recorder.read().unwrap().updates.len(), 1);
+        // A scene pushed on top stops the scene below from updating.
+        game.push_scene(SceneEntities::default());
+        game.step(0.0);
+        assert_eq!(recorder.read().unwrap().updates.len(), 2);
+        assert_eq!(scene_recorder.read().unwrap().updates.len(), 1);
+        assert!(game.pop_scene().is_some());
+        game.step(0.0);
+        assert_eq!(scene_recorder.read().unwrap().updates.len(), 2);
+    }
+
+    #[test]
+    fn game_scene_control() {
+        let mut game = Game::default();
+        let control = game.scene_control();
+        control.push(SceneEntities::default());
+        assert_eq!(game.scene_count(), 0);
+        // Requests are applied at the end of the step.
+        game.step(0.0);
+        assert_eq!(game.scene_count(), 1);
+    }
+
+    #[test]
+    fn game_control() {
+        let (mut game, recorder) = recorder_game(FixedUpdateMode::Accumulated);
+        let control = game.control();
+        control.pause();
+        control.set_timestep(20);
+        // Requests take effect at the end of the step.
+        game.step(0.01);
+        assert!(game.is_paused());
+        assert_eq!(game.timestep, 20);
+        assert_eq!(recorder.read().unwrap().updates.len(), 1);
+        // No entities are updated while paused.
+        game.step(0.05);
+        assert_eq!(recorder.read().unwrap().updates.len(), 1);
+        assert_eq!(recorder.read().unwrap().fixed_updates.len(), 1);
+        control.resume();
+        control.quit();
+        assert_eq!(game.exit_reason(), None);
+        game.step(0.0);
+        assert!(!game.is_paused());
+        assert_eq!(game.exit_reason(), Some(ExitReason::Quit));
+    }
+
+    #[test]
+    fn game_time_scale() {
+        let (mut game, recorder) = recorder_game(FixedUpdateMode::Accumulated);
+        game.set_time_scale(2.0);
+        assert_eq!(game.step(0.01), 2);
+        assert_eq!(recorder.read().unwrap().updates, vec![0.02]);
+        game.set_paused(true);
+        assert_eq!(game.step(0.01), 0);
+        assert_eq!(recorder.read().unwrap().updates.len(), 1);
+    }
+
+    #[test]
+    fn game_unscaled_time() {
+        struct Menu(Vec<f32>, usize);
+        impl Update for Menu {
+            fn update(&mut self, delta: f32) -> CtraitResult<()> {
+                self.0.push(delta);
+                Ok(())
+            }
+            fn unscaled_time(&self) -> bool {
+                true
+            }
+        }
+        impl FixedUpdate for Menu {
+            fn fixed_update(&mut self, _: f32) -> CtraitResult<()> {
+                self.1 += 1;
+                Ok(())
+            }
+            fn unscaled_time(&self) -> bool {
+                true
+            }
+        }
+        let menu = crate::entity!(Menu(Vec::new(), 0));
+        let mut game = Game::default().with_timestep(10);
+        game.update_entities
+            .add_entities(&[Entity::clone(&menu) as Entity<dyn Update>]);
+        game.fixed_update_entities
+            .add_entities(&[Entity::clone(&menu) as Entity<dyn FixedUpdate>]);
+        game.set_time_scale(0.5);
+        game.step(0.02);
+        game.set_paused(true);
+        game.step(0.02);
+        // Only scaled fixed steps are counted.
+        assert_eq!(game.stats().fixed_steps, 0);
+        // Unscaled entities receive real time, even while paused.
+        let menu = menu.read().unwrap();
+        assert_eq!(menu.0, vec![0.02, 0.02]);
+        assert_eq!(menu.1, 4);
+    }
+
+    #[test]
+    fn game_destroy_entities() {
+        struct Counter(Arc<AtomicUsize>);
+        impl Destroy for Counter {
+            fn destroy(&mut self) -> CtraitResult<()> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+        let destroyed = Arc::new(AtomicUsize::new(0));
+        let counter = crate::entity!(Counter(Arc::clone(&destroyed)));
+        let mut game = Game::default();
+        game.destroy_entities
+            .add_entities(&[Entity::clone(&counter) as Entity<dyn Destroy>]);
+        game.step(0.0);
+        assert_eq!(destroyed.load(Ordering::SeqCst), 0);
+        drop(counter);
+        game.step(0.0);
+        game.step(0.0);
+        // Destroyed exactly once.
+        assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn game_commands() {
+        struct Spawner {
+            commands: Commands,
+            update_entities: Entities<dyn Update>,
+            spawned: Vec<Entity<Recorder>>,
+        }
+        impl Update for Spawner {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                let recorder = crate::entity!(Recorder::default());
+                // Would deadlock without commands, as update_entities is being iterated.
+                self.commands.add_entities(
+                    &self.update_entities,
+                    &[Entity::clone(&recorder) as Entity<dyn Update>],
+                );
+                self.spawned.push(recorder);
+                Ok(())
+            }
+        }
+        let mut game = Game::default();
+        let spawner = crate::entity!(Spawner {
+            commands: game.commands(),
+            update_entities: Entities::clone(&game.update_entities),
+            spawned: Vec::new(),
+        });
+        game.update_entities
+            .add_entities(&[Entity::clone(&spawner) as Entity<dyn Update>]);
+        game.step(0.0);
+        game.step(0.0);
+        let first = Entity::clone(&spawner.read().unwrap().spawned[0]);
+        assert_eq!(first.read().unwrap().updates.len(), 1);
+        game.commands().despawn(&first);
+        game.step(0.0);
+        // The despawned entity is no longer updated.
+        assert_eq!(first.read().unwrap().updates.len(), 1);
+        // The spawner and the two entities spawned after the first one.
+        assert_eq!(game.update_entities.access().lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn game_despawn() {
+        struct Counter(Arc<AtomicUsize>);
+        impl Destroy for Counter {
+            fn destroy(&mut self) -> CtraitResult<()> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+        impl Update for Counter {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        let destroyed = Arc::new(AtomicUsize::new(0));
+        let counter = crate::entity!(Counter(Arc::clone(&destroyed)));
+        let mut game = Game::default();
+        let scene = SceneEntities::default();
+        scene
+            .update_entities
+            .clone()
+            .add_entities(&[Entity::clone(&counter) as Entity<dyn Update>]);
+        game.push_scene(SceneEntities::clone(&scene));
+        game.update_entities
+            .add_entities(&[Entity::clone(&counter) as Entity<dyn Update>]);
+        game.destroy_entities
+            .add_entities(&[Entity::clone(&counter) as Entity<dyn Destroy>]);
+        game.despawn(&counter);
+        assert!(game.update_entities.access().lock().unwrap().is_empty());
+        assert!(scene.update_entities.access().lock().unwrap().is_empty());
+        assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn game_lookup() {
+        struct Enemy;
+        impl Update for Enemy {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        let enemy = crate::entity!(Enemy);
+        let mut game = Game::default();
+        let scene = SceneEntities::default();
+        scene
+            .update_entities
+            .clone()
+            .add_entities(&[Entity::clone(&enemy) as Entity<dyn Update>]);
+        game.push_scene(scene);
+        let id = game.entity_ids().id(&enemy);
+        let found = game.lookup::<dyn Update>(id).unwrap();
+        assert_eq!(entity::address(&found), entity::address(&enemy));
+        assert!(game.lookup::<dyn LateUpdate>(id).is_none());
+
+        game.despawn(&enemy);
+        assert!(!game.entity_ids().is_alive(id));
+        assert!(game.lookup::<dyn Update>(id).is_none());
+        // The entity is assigned a new identifier if registered again.
+        assert_ne!(game.entity_ids().id(&enemy), id);
+    }
+
+    #[test]
+    fn game_entity_ids_assigned() {
+        struct Enemy;
+        impl Update for Enemy {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        crate::spawnable!(Enemy: Update);
+        let (added, registered, spawned) = (
+            crate::entity!(Enemy),
+            crate::entity!(Enemy),
+            crate::entity!(Enemy),
+        );
+        let mut game = Game::default();
+        let ids = game.entity_ids();
+        game.update_entities
+            .add_entities(&[Entity::clone(&added) as Entity<dyn Update>]);
+        assert!(ids.get(&added).is_some());
+        assert!(ids.get(&registered).is_none());
+        game.register(&(Entity::clone(&registered) as Entity<dyn Update>));
+        assert!(ids.get(&registered).is_some());
+        // Identifiers are assigned in the order entities are added.
+        let id = game.spawn(&spawned);
+        assert_eq!(ids.get(&spawned), Some(id));
+        assert!(ids.get(&registered).unwrap().index() < id.index());
+    }
+
+    #[test]
+    fn game_spawn() {
+        struct Ball;
+        impl Update for Ball {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        impl FixedUpdate for Ball {
+            fn fixed_update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        impl Destroy for Ball {
+            fn destroy(&mut self) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        crate::spawnable!(Ball: Update, FixedUpdate, Destroy, FixedUpdate);
+
+        let mut game = Game::default();
+        let ball = crate::entity!(Ball);
+        let id = game.spawn(&ball);
+        assert_eq!(game.spawn(&ball), id);
+        assert_eq!(game.update_entities.len(), 1);
+        assert_eq!(game.fixed_update_entities.len(), 1);
+        assert!(game.destroy_entities.contains(&ball));
+        assert!(game.late_update_entities.is_empty());
+        // Registering an entity already spawned has no effect.
+        assert!(!game.register(&(Entity::clone(&ball) as Entity<dyn Update>)));
+
+        let other = crate::entity!(Ball);
+        game.commands().spawn(&other);
+        game.step(0.0);
+        assert!(game.update_entities.contains(&other));
+        assert_eq!(game.update_entities.len(), 2);
+    }
+
+    #[test]
+    fn game_spawn_unlisted() {
+        struct Ball;
+        impl Update for Ball {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        impl LateUpdate for Ball {
+            fn late_update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        // LateUpdate is not listed, but the ball is still registered as one.
+        crate::spawnable!(Ball: Update);
+
+        let mut game = Game::default();
+        let ball = crate::entity!(Ball);
+        game.spawn(&ball);
+        assert!(game.update_entities.contains(&ball));
+        assert!(game.late_update_entities.contains(&ball));
+        assert!(game.fixed_update_entities.is_empty());
+    }
+
+    #[test]
+    fn game_systems() {
+        struct Position(f32);
+        struct Velocity(f32);
+
+        let mut game = Game::default();
+        let a = game.world_mut().spawn((Position(0.0), Velocity(1.0)));
+        let b = game.world_mut().spawn((Position(0.0),));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for (phase, name) in [
+            (SystemPhase::LateUpdate, "late_update"),
+            (SystemPhase::Update, "update"),
+            (SystemPhase::FixedUpdate, "fixed_update"),
+            (SystemPhase::PreUpdate, "pre_update"),
+        ] {
+            let order = Arc::clone(&order);
+            game.add_system(phase, move |_, _| {
+                order.lock().unwrap().push(name);
+                Ok(())
+            });
+        }
+        game.add_system(SystemPhase::Update, |world, delta| {
+            world
+                .query::<(&mut Position, &Velocity)>()
+                .for_each(|(position, velocity)| position.0 += velocity.0 * delta);
+            Err(CtraitError::Other(String::from("failed")))
+        });
+        game.step(game.timestep_secs());
+        assert_eq!(
+            *order.lock().unwrap(),
+            ["pre_update", "fixed_update", "update", "late_update"]
+        );
+        assert!((game.world().get::<Position>(a).unwrap().0 - game.timestep_secs()).abs() < 1e-6);
+        assert!(game.world().get::<Position>(b).unwrap().0.abs() < f32::EPSILON);
+        let error = game.take_error().unwrap();
+        assert_eq!(error.phase, "update");
+
+        // Systems are not run while the game is paused.
+        game.set_paused(true);
+        game.step(game.timestep_secs());
+        assert_eq!(order.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn game_system_panic() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut game = Game::default().with_panic_policy(PanicPolicy::Remove);
+        let clone = Arc::clone(&calls);
+        game.add_system(SystemPhase::Update, move |_, _| {
+            clone.fetch_add(1, Ordering::SeqCst);
+            panic!("system failed");
+        });
+        game.step(0.0);
+        // The system which panicked is removed.
+        game.step(0.0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn game_poisoned_container() {
+        struct Counter(u32);
+        impl Update for Counter {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                self.0 += 1;
+                Ok(())
+            }
+        }
+        let counter = crate::entity!(Counter(0));
+        let mut game = Game::default();
+        game.update_entities
+            .add_entities(&[Entity::clone(&counter) as Entity<dyn Update>]);
+        let access = Arc::clone(game.update_entities.access());
+        let _ = std::thread::spawn(move || {
+            let _guard = access.lock().unwrap();
+            panic!("poison");
+        })
+        .join();
+        // A poisoned container keeps being dispatched.
+        game.step(0.0);
+        assert_eq!(counter.read().unwrap().0, 1);
+    }
+
+    #[test]
+    fn game_inactive() {
+        #[derive(Default)]
+        struct Frozen {
+            updates: u32,
+            fixed_updates: u32,
+            events: u32,
+        }
+        impl Update for Frozen {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                self.updates += 1;
+                Ok(())
+            }
+
+            fn update_active(&self) -> bool {
+                false
+            }
+        }
+        impl FixedUpdate for Frozen {
+            fn fixed_update(&mut self, _: f32) -> CtraitResult<()> {
+                self.fixed_updates += 1;
+                Ok(())
+            }
+        }
+        impl Interactive for Frozen {
+            fn on_event(&mut self, _: &Event) -> CtraitResult<()> {
+                self.events += 1;
+                Ok(())
+            }
+
+            fn event_active(&self) -> bool {
+                false
+            }
+        }
+        crate::spawnable!(Frozen: Update, FixedUpdate, Interactive);
+
+        let mut game = Game::default();
+        let frozen = crate::entity!(Frozen::default());
+        game.spawn(&frozen);
+        game.step(game.timestep_secs());
+        game.inject_event(Event::Quit { timestamp: 0 });
+        let frozen = frozen.read().unwrap();
+        assert_eq!(frozen.updates, 0);
+        assert_eq!(frozen.fixed_updates, 1);
+        assert_eq!(frozen.events, 0);
+        // Inactive entities are kept in their containers.
+        assert_eq!(game.update_entities.len(), 1);
+    }
+
+    #[test]
+    fn game_hierarchy() {
+        struct Node {
+            local: Transform,
+            world: Transform,
+        }
+        impl Spatial for Node {
+            fn local_transform(&self) -> Transform {
+                self.local
+            }
+
+            fn set_world_transform(&mut self, transform: Transform) {
+                self.world = transform;
+            }
+        }
+        impl Update for Node {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        crate::spawnable!(Node: Update);
+        let node = |x| {
+            crate::entity!(Node {
+                local: Transform::new(Vector2::new(x, 0.0)),
+                world: Transform::default(),
+            })
+        };
+
+        let mut game = Game::default();
+        let (parent, child, grandchild) = (node(1.0), node(2.0), node(3.0));
+        for entity in [&parent, &child, &grandchild] {
+            game.spawn(entity);
+        }
+        let hierarchy = game.hierarchy();
+        let spatial: Vec<Entity<dyn Spatial>> = vec![
+            Entity::<Node>::clone(&parent),
+            Entity::<Node>::clone(&child),
+            Entity::<Node>::clone(&grandchild),
+        ];
+        hierarchy.set_parent(&spatial[1], &spatial[0]).unwrap();
+        hierarchy.set_parent(&spatial[2], &spatial[1]).unwrap();
+        drop(spatial);
+        game.step(0.0);
+        assert_eq!(grandchild.read().unwrap().world.position.x, 6.0);
+
+        // Despawning the parent despawns its descendants.
+        game.despawn(&parent);
+        assert!(game.update_entities.is_empty());
+        assert!(hierarchy.parent(&grandchild).is_none());
+    }
+
+    #[test]
+    fn game_registry() {
+        struct Enemy;
+        impl Update for Enemy {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+        crate::spawnable!(Enemy: Update);
+
+        let mut game = Game::default();
+        let (boss, minion) = (crate::entity!(Enemy), crate::entity!(Enemy));
+        game.spawn(&boss);
+        game.spawn(&minion);
+        let registry = game.registry();
+        registry.set_name(&boss, "boss").unwrap();
+        registry.add_tag(&boss, "enemy");
+        registry.add_tag(&minion, "enemy");
+        let found = game.find_by_name::<Enemy>("boss").unwrap();
+        assert_eq!(entity::address(&found), entity::address(&boss));
+        drop(found);
+        assert_eq!(game.with_tag::<Enemy>("enemy").len(), 2);
+
+        game.despawn(&boss);
+        assert!(game.find_by_name::<Enemy>("boss").is_none());
+        assert_eq!(game.with_tag::<Enemy>("enemy").len(), 1);
+    }
+
+    #[test]
+    fn game_event_bus() {
+        struct Died;
+        struct Player {
+            bus: EventBus,
+        }
+        impl Update for Player {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                self.bus.emit(Died);
+                Ok(())
+            }
+        }
+        crate::spawnable!(Player: Update);
+
+        let errors = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&errors);
+        let mut game = Game::default().with_error_handler(move |error| {
+            assert_eq!(error.phase, "event_bus");
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let bus = game.event_bus();
+        let player = crate::entity!(Player {
+            bus: EventBus::clone(&bus),
+        });
+        game.spawn(&player);
+        let deaths = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&deaths);
+        bus.subscribe(move |_: &Died| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Err(CtraitError::Other(String::from("game over")))
+        });
+        // Events emitted in the update are delivered in the same frame.
+        game.step(0.1);
+        assert_eq!(deaths.load(Ordering::SeqCst), 1);
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+        assert_eq!(bus.pending(), 0);
+    }
+
+    #[test]
+    fn game_inject_event() {
+        let (mut game, recorder) = recorder_game(FixedUpdateMode::Accumulated);
+        game.inject_event(Event::Quit { timestamp: 0 });
+        game.inject_event(Event::Quit { timestamp: 1 });
+        assert_eq!(recorder.read().unwrap().events, 2);
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// Paces frames to a target frame rate by sleeping, then spinning for the last moments before the
+// deadline as sleeping is not precise enough.
+#[derive(Debug)]
+pub(crate) struct FramePacer {
+    deadline: Instant,
+}
+
+impl FramePacer {
+    // Time before the deadline during which the pacer spins instead of sleeping.
+    const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+    pub(crate) fn new() -> Self {
+        Self {
+            deadline: Instant::now(),
+        }
+    }
+
+    // Wait until a frame at the given rate has passed since the last frame.
+    // If there is no frame rate, returns immediately.
+    pub(crate) fn wait(&mut self, fps: Option<u32>) {
+        let now = Instant::now();
+        let fps = match fps {
+            Some(fps) if fps > 0 => fps,
+            _ => {
+                self.deadline = now;
+                return;
+            }
+        };
+        let deadline = self.deadline + Duration::from_secs_f64(1.0 / f64::from(fps));
+        if deadline <= now {
+            // The frame took longer than the target. Do not try to catch up.
+            self.deadline = now;
+            return;
+        }
+        let remaining = deadline - now;
+        if remaining > Self::SPIN_MARGIN {
+            thread::sleep(remaining - Self::SPIN_MARGIN);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+        self.deadline = deadline;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FramePacer;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn frame_pacer_wait() {
+        let mut pacer = FramePacer::new();
+        let instant = Instant::now();
+        pacer.wait(Some(100));
+        pacer.wait(Some(100));
+        assert!(instant.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn frame_pacer_unlimited() {
+        let mut pacer = FramePacer::new();
+        let instant = Instant::now();
+        pacer.wait(None);
+        pacer.wait(Some(0));
+        assert!(instant.elapsed() < Duration::from_millis(10));
+    }
+}
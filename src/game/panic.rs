@@ -0,0 +1,250 @@
+use crate::{
+    command::Commands,
+    deadlock,
+    entity::{self, Entity},
+    error::{CtraitResult, EntityError},
+    traits::{
+        Destroy, FixedUpdate, Interactive, LateUpdate, PreUpdate, Renderable, Spatial, Start,
+        TypeName, Update,
+    },
+};
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// How [`Game`](crate::game::Game) handles an entity panicking in one of its trait methods.
+///
+/// Whatever the policy, the panic is reported on the standard error with the entity's type name
+/// and the phase it panicked in. Entities which panicked while holding the write lock of another
+/// entity poison it; poisoned entities are recovered by the game and keep being dispatched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Resume the panic, which stops the game.
+    #[default]
+    Abort,
+    /// Keep the entity, skipping the call which panicked.
+    Skip,
+    /// Remove the entity from every container, as [`Game::despawn`](crate::game::Game::despawn)
+    /// does, at the end of the current phase.
+    Remove,
+}
+
+// A trait object type which can be dispatched by the game.
+pub(crate) trait Dispatched: TypeName {
+    // Name of the phase in which the trait is dispatched.
+    const PHASE: &'static str;
+}
+
+macro_rules! dispatched {
+    ($($name:ident => $phase:literal),+) => {
+        $(
+            impl Dispatched for dyn $name {
+                const PHASE: &'static str = $phase;
+            }
+        )+
+    };
+}
+
+dispatched!(
+    PreUpdate => "pre_update",
+    Update => "update",
+    LateUpdate => "late_update",
+    FixedUpdate => "fixed_update",
+    Interactive => "on_event",
+    Renderable => "render",
+    Start => "start",
+    Destroy => "destroy",
+    Spatial => "set_world_transform"
+);
+
+// Calls into entities, isolating their panics according to a policy.
+#[derive(Clone)]
+pub(crate) struct Isolation {
+    pub(crate) policy: PanicPolicy,
+    // Used to remove entities which panicked.
+    pub(crate) commands: Commands,
+}
+
+impl Isolation {
+    // Lock the entity for writing and call the given function on it.
+    // Returned errors are reported to the game at the end of the current phase.
+    pub(crate) fn call<T: ?Sized + Dispatched + Send + Sync + 'static>(
+        &self,
+        entity: &Entity<T>,
+        call: impl FnOnce(&mut T) -> CtraitResult<()>,
+    ) {
+        let _phase = deadlock::enter(T::PHASE);
+        let mut guard = entity::write(entity);
+        let type_name = TypeName::type_name(&*guard);
+        guard.set_type_name(type_name);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| call(&mut guard)));
+        drop(guard);
+        self.handle(entity, type_name, result);
+    }
+
+    // Like call, but locks the entity for reading, so other readers are not blocked.
+    pub(crate) fn call_read<T: ?Sized + Dispatched + Send + Sync + 'static>(
+        &self,
+        entity: &Entity<T>,
+        call: impl FnOnce(&T) -> CtraitResult<()>,
+    ) {
+        let _phase = deadlock::enter(T::PHASE);
+        let guard = entity::read(entity);
+        let type_name = TypeName::type_name(&*guard);
+        guard.set_type_name(type_name);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| call(&guard)));
+        drop(guard);
+        self.handle(entity, type_name, result);
+    }
+
+    // Call a function which is not bound to an entity, such as a subscriber of the event bus,
+    // isolating its panic. Returns `false` if the function panicked and should be dropped, as
+    // requested by PanicPolicy::Remove.
+    pub(crate) fn call_fn(
+        &self,
+        type_name: &'static str,
+        phase: &'static str,
+        call: impl FnOnce() -> CtraitResult<()>,
+    ) -> bool {
+        let result = panic::catch_unwind(AssertUnwindSafe(call));
+        self.report(type_name, phase, result)
+    }
+
+    // Report the error or the panic of an entity call according to the policy.
+    fn handle<T: ?Sized + Dispatched + Send + Sync + 'static>(
+        &self,
+        entity: &Entity<T>,
+        type_name: &'static str,
+        result: std::thread::Result<CtraitResult<()>>,
+    ) {
+        if !self.report(type_name, T::PHASE, result) {
+            self.commands.despawn(entity);
+        }
+    }
+
+    // Report the error or the panic of a call according to the policy. Returns `false` if the
+    // caller panicked and should be removed.
+    fn report(
+        &self,
+        type_name: &'static str,
+        phase: &'static str,
+        result: std::thread::Result<CtraitResult<()>>,
+    ) -> bool {
+        match result {
+            Ok(Ok(())) => true,
+            Ok(Err(error)) => {
+                let error = EntityError {
+                    type_name,
+                    phase,
+                    error,
+                };
+                self.commands.push(move |game| game.report_error(error));
+                true
+            }
+            Err(payload) => {
+                eprintln!(
+                    "entity of type `{}` panicked in {}: {}",
+                    type_name,
+                    phase,
+                    message(&*payload)
+                );
+                match self.policy {
+                    PanicPolicy::Abort => panic::resume_unwind(payload),
+                    PanicPolicy::Skip => true,
+                    PanicPolicy::Remove => false,
+                }
+            }
+        }
+    }
+}
+
+// Message of a panic payload.
+fn message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{message, CtraitResult, Isolation, PanicPolicy};
+    use crate::{command::Commands, entity::Entity, traits::Update};
+    use std::{panic, sync::Arc};
+
+    struct Panicking;
+
+    impl Update for Panicking {
+        fn update(&mut self, _: f32) -> CtraitResult<()> {
+            panic!("update failed");
+        }
+    }
+
+    fn isolation(policy: PanicPolicy) -> Isolation {
+        Isolation {
+            policy,
+            commands: Commands::default(),
+        }
+    }
+
+    #[test]
+    fn isolation_skip() {
+        let entity: Entity<dyn Update> = crate::entity!(Panicking);
+        let isolation = isolation(PanicPolicy::Skip);
+        isolation.call(&entity, |entity| entity.update(0.0));
+        assert!(!entity.is_poisoned());
+        assert!(isolation.commands.take().is_empty());
+    }
+
+    #[test]
+    fn isolation_remove() {
+        let entity: Entity<dyn Update> = crate::entity!(Panicking);
+        let isolation = isolation(PanicPolicy::Remove);
+        isolation.call(&entity, |entity| entity.update(0.0));
+        assert_eq!(isolation.commands.take().len(), 1);
+    }
+
+    #[test]
+    fn isolation_abort() {
+        let entity: Entity<dyn Update> = crate::entity!(Panicking);
+        let isolation = isolation(PanicPolicy::Abort);
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            isolation.call(&entity, |entity| entity.update(0.0));
+        }));
+        assert_eq!(message(&*result.unwrap_err()), "update failed");
+    }
+
+    #[test]
+    fn isolation_poisoned() {
+        let entity: Entity<dyn Update> = crate::entity!(Panicking);
+        let clone = Arc::clone(&entity);
+        let _ = std::thread::spawn(move || {
+            let _guard = clone.write().unwrap();
+            panic!("poison");
+        })
+        .join();
+        assert!(entity.is_poisoned());
+        // Poisoned entities are still dispatched.
+        let mut called = false;
+        isolation(PanicPolicy::Skip).call(&entity, |_| {
+            called = true;
+            Ok(())
+        });
+        assert!(called);
+    }
+
+    #[test]
+    fn isolation_call_read() {
+        let entity: Entity<dyn Update> = crate::entity!(Panicking);
+        // Reading does not wait for other readers, such as another rendering thread.
+        let _guard = entity.read().unwrap();
+        let mut called = false;
+        isolation(PanicPolicy::Skip).call_read(&entity, |_| {
+            called = true;
+            Ok(())
+        });
+        assert!(called);
+    }
+}
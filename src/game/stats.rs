@@ -0,0 +1,181 @@
+use crate::error::CtraitResult;
+use sdl2::{pixels::Color, rect::Rect, render::WindowCanvas};
+use std::time::{Duration, Instant};
+
+// Weight of the latest frame in the averaged frame rate.
+const FPS_SMOOTHING: f32 = 0.1;
+// Frame time matching the full width of an overlay bar.
+const OVERLAY_BUDGET: f32 = 1.0 / 60.0;
+const OVERLAY_WIDTH: f32 = 120.0;
+const OVERLAY_ROW: u32 = 6;
+const OVERLAY_MARGIN: i32 = 8;
+
+/// Time spent in each phase of a frame.
+///
+/// See the [phase order](crate::game::Game#phase-order). Commands applied at the end of a phase
+/// are included in the time of that phase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimes {
+    /// Time spent polling and dispatching events.
+    pub events: Duration,
+    /// Time spent starting new entities.
+    pub start: Duration,
+    /// Time spent destroying released entities.
+    pub destroy: Duration,
+    /// Time spent in pre update entities.
+    pub pre_update: Duration,
+    /// Time spent in fixed update entities.
+    ///
+    /// This is always zero with [`FixedUpdateMode::Threaded`](crate::game::FixedUpdateMode),
+    /// as fixed steps are run on the timer thread.
+    pub fixed_update: Duration,
+    /// Time spent in callbacks of the [`Scheduler`](crate::scheduler::Scheduler).
+    pub timers: Duration,
+    /// Time spent in update entities.
+    pub update: Duration,
+    /// Time spent in late update entities.
+    pub late_update: Duration,
+    /// Time spent rendering, including presenting the canvas.
+    pub render: Duration,
+}
+
+impl PhaseTimes {
+    // Phases with their overlay color, in phase order.
+    fn rows(&self) -> [(Duration, Color); 9] {
+        [
+            (self.events, Color::RGB(255, 128, 0)),
+            (self.start, Color::RGB(128, 128, 128)),
+            (self.destroy, Color::RGB(128, 0, 0)),
+            (self.pre_update, Color::RGB(0, 128, 255)),
+            (self.fixed_update, Color::RGB(0, 255, 0)),
+            (self.timers, Color::RGB(255, 0, 255)),
+            (self.update, Color::RGB(0, 255, 255)),
+            (self.late_update, Color::RGB(0, 0, 255)),
+            (self.render, Color::RGB(255, 255, 0)),
+        ]
+    }
+}
+
+/// Number of entities in the containers of the game and of the active scenes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntityCounts {
+    /// Number of pre update entities.
+    pub pre_update: usize,
+    /// Number of update entities.
+    pub update: usize,
+    /// Number of late update entities.
+    pub late_update: usize,
+    /// Number of fixed update entities.
+    pub fixed_update: usize,
+    /// Number of renderable entities.
+    pub renderable: usize,
+    /// Number of interactive entities.
+    pub interactive: usize,
+}
+
+/// Timing statistics of the last frame of a [`Game`](crate::game::Game).
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::game::Game;
+///
+/// let mut game = Game::new().with_timestep(100);
+/// game.step(0.25);
+/// let stats = game.stats();
+/// assert_eq!(stats.fixed_steps, 2);
+/// assert_eq!(stats.entity_counts.update, 0);
+/// println!("update took {:?}", stats.phases.update);
+/// ```
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    /// Duration of the last frame, which is the delta passed to
+    /// [`Game::step`](crate::game::Game::step).
+    pub frame_time: Duration,
+    /// Frames per second, averaged over the last frames.
+    pub fps: f32,
+    /// Number of fixed steps run during the last frame, according to the game's time scale.
+    ///
+    /// Entities with [`FixedUpdate::unscaled_time`](crate::traits::FixedUpdate::unscaled_time)
+    /// are stepped in separate passes according to unscaled time, which are not counted: while
+    /// the game is paused, this is 0 even if these entities were stepped.
+    pub fixed_steps: u32,
+    /// Time spent in each phase of the last frame.
+    pub phases: PhaseTimes,
+    /// Number of entities in each kind of container.
+    pub entity_counts: EntityCounts,
+    /// Time spent rendering each type of renderable entity during the last frame, slowest
+    /// first.
+    ///
+    /// Types are identified by [`TypeName::type_name`](crate::traits::TypeName::type_name).
+    pub render_times: Vec<(&'static str, Duration)>,
+}
+
+impl FrameStats {
+    // Record the duration of a new frame.
+    pub(crate) fn record_frame(&mut self, frame_time: Duration) {
+        self.frame_time = frame_time;
+        let secs = frame_time.as_secs_f32();
+        if secs > 0.0 {
+            let fps = 1.0 / secs;
+            self.fps = if self.fps > 0.0 {
+                self.fps + (fps - self.fps) * FPS_SMOOTHING
+            } else {
+                fps
+            };
+        }
+    }
+
+    // Draw a bar for the frame time followed by a bar for every phase, in phase order.
+    // A full bar corresponds to a frame at 60 frames per second.
+    pub(crate) fn draw_overlay(&self, canvas: &mut WindowCanvas) -> CtraitResult<()> {
+        let rows = std::iter::once((self.frame_time, Color::WHITE)).chain(self.phases.rows());
+        let mut y = OVERLAY_MARGIN;
+        for (time, color) in rows {
+            let width = (time.as_secs_f32() / OVERLAY_BUDGET * OVERLAY_WIDTH) as u32;
+            canvas.set_draw_color(color);
+            canvas.fill_rect(Rect::new(OVERLAY_MARGIN, y, width.max(1), OVERLAY_ROW))?;
+            y += OVERLAY_ROW as i32 + 2;
+        }
+        let budget = OVERLAY_MARGIN + OVERLAY_WIDTH as i32;
+        canvas.set_draw_color(Color::GRAY);
+        canvas.draw_line((budget, OVERLAY_MARGIN), (budget, y))?;
+        Ok(())
+    }
+}
+
+// Returns the time elapsed since the given instant, and resets it to now.
+pub(crate) fn lap(instant: &mut Instant) -> Duration {
+    let now = Instant::now();
+    let elapsed = now - *instant;
+    *instant = now;
+    elapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lap, FrameStats};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn frame_stats_record_frame() {
+        let mut stats = FrameStats::default();
+        stats.record_frame(Duration::from_millis(10));
+        assert!((stats.fps - 100.0).abs() < 0.01);
+        stats.record_frame(Duration::from_millis(20));
+        assert_eq!(stats.frame_time, Duration::from_millis(20));
+        // The frame rate is smoothed.
+        assert!(stats.fps > 50.0 && stats.fps < 100.0);
+        stats.record_frame(Duration::ZERO);
+        assert!(stats.fps > 50.0);
+    }
+
+    #[test]
+    fn stats_lap() {
+        let mut instant = Instant::now();
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(lap(&mut instant) >= Duration::from_millis(2));
+        assert!(lap(&mut instant) < Duration::from_millis(2));
+    }
+}
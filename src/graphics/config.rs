@@ -49,6 +49,16 @@ pub struct RendererConfig {
     pub accelerated: bool,
     /// Use VSync.
     pub present_vsync: bool,
+    /// Maximum number of frames per second.
+    ///
+    /// The game loop sleeps at the end of each frame to not exceed this rate. If this is
+    /// [`None`], frames are run as fast as possible, or as fast as VSync allows if
+    /// [`present_vsync`](Self::present_vsync) is set.
+    pub target_fps: Option<u32>,
+    /// Maximum number of frames per second while the window is unfocused or minimized.
+    ///
+    /// If this is [`None`], [`target_fps`](Self::target_fps) is used instead.
+    pub background_fps: Option<u32>,
 }
 
 impl RendererConfig {
@@ -57,6 +67,31 @@ impl RendererConfig {
     /// Default window height.
     pub const FALLBACK_HEIGHT: u32 = 480;
 
+    /// Get the frame rate limit for a window that is in the foreground or in the background
+    /// (unfocused or minimized).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::graphics::RendererConfig;
+    ///
+    /// let config = RendererConfig {
+    ///     target_fps: Some(144),
+    ///     background_fps: Some(10),
+    ///     ..RendererConfig::default()
+    /// };
+    /// assert_eq!(config.frame_rate(false), Some(144));
+    /// assert_eq!(config.frame_rate(true), Some(10));
+    /// ```
+    #[must_use]
+    pub fn frame_rate(&self, background: bool) -> Option<u32> {
+        if background {
+            self.background_fps.or(self.target_fps)
+        } else {
+            self.target_fps
+        }
+    }
+
     /// Get the dimensions specified in the configuration. If dimensions is [`None`], returns
     /// fallback dimensions derived from [`FALLBACK_WIDTH`](Self::FALLBACK_WIDTH) and [`FALLBACK_HEIGHT`](Self::FALLBACK_HEIGHT).
     #[must_use]
@@ -121,6 +156,8 @@ impl Default for RendererConfig {
             vulkan: false,
             accelerated: false,
             present_vsync: false,
+            target_fps: None,
+            background_fps: None,
         }
     }
 }
@@ -138,6 +175,17 @@ mod tests {
         assert_eq!(config.dimensions(), (5, 10));
     }
 
+    #[test]
+    fn renderer_config_frame_rate() {
+        let config = RendererConfig {
+            target_fps: Some(60),
+            ..RendererConfig::default()
+        };
+        // Fall back to the target frame rate without a background frame rate.
+        assert_eq!(config.frame_rate(true), Some(60));
+        assert_eq!(RendererConfig::default().frame_rate(false), None);
+    }
+
     #[test]
     fn renderer_config_fallback_dimensions() {
         let config = RendererConfig {
@@ -0,0 +1,215 @@
+use std::sync::{Arc, Mutex};
+
+// A named render layer.
+#[derive(Debug, Clone)]
+pub(crate) struct Layer {
+    pub(crate) name: String,
+    pub(crate) visible: bool,
+}
+
+/// Ordered set of named render layers, from the bottom to the top.
+///
+/// Each [`Renderable`](crate::traits::Renderable) entity is rendered in the layer named by
+/// [`Renderable::layer`](crate::traits::Renderable::layer). Layers are rendered from the bottom
+/// to the top, and entities in a hidden layer are not rendered. Entities in a layer which is not
+/// part of the set are rendered as if they were in the [`DEFAULT`](Self::DEFAULT) layer, or on
+/// top of every layer if the default layer was removed.
+///
+/// The set initially consists of the default layer only. It is owned by the
+/// [`Renderer`](crate::graphics::Renderer), see
+/// [`Renderer::layers`](crate::graphics::Renderer::layers). The handle can be cloned and stored
+/// in entities to change the layers at runtime.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::graphics::Layers;
+///
+/// let layers = Layers::new();
+/// layers.add("ui");
+/// // Render the background below the default layer.
+/// layers.move_to("background", 0);
+/// assert_eq!(layers.names(), ["background", Layers::DEFAULT, "ui"]);
+///
+/// layers.set_visible("ui", false);
+/// assert!(!layers.is_visible("ui"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Layers(Arc<Mutex<Vec<Layer>>>);
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layers {
+    /// Name of the layer entities are rendered in by default.
+    pub const DEFAULT: &'static str = "default";
+
+    /// Constructs a new set of layers, consisting of the default layer.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(vec![Layer {
+            name: Self::DEFAULT.to_string(),
+            visible: true,
+        }])))
+    }
+
+    /// Add a visible layer on top of every other layer.
+    ///
+    /// Does nothing if the layer is already part of the set.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn add(&self, name: &str) {
+        let mut layers = self.0.lock().unwrap();
+        if !layers.iter().any(|layer| layer.name == name) {
+            layers.push(Layer {
+                name: name.to_string(),
+                visible: true,
+            });
+        }
+    }
+
+    /// Move the layer to the given position, `0` being the bottom.
+    ///
+    /// The layer is added as a visible layer if it is not part of the set. Positions past the top
+    /// move the layer to the top.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn move_to(&self, name: &str, position: usize) {
+        let mut layers = self.0.lock().unwrap();
+        let layer = match layers.iter().position(|layer| layer.name == name) {
+            Some(index) => layers.remove(index),
+            None => Layer {
+                name: name.to_string(),
+                visible: true,
+            },
+        };
+        let position = position.min(layers.len());
+        layers.insert(position, layer);
+    }
+
+    /// Remove the layer from the set. Returns `true` if it was part of the set.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn remove(&self, name: &str) -> bool {
+        let mut layers = self.0.lock().unwrap();
+        let len = layers.len();
+        layers.retain(|layer| layer.name != name);
+        layers.len() != len
+    }
+
+    /// Show or hide the layer.
+    ///
+    /// The layer is added on top of every other layer if it is not part of the set.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn set_visible(&self, name: &str, visible: bool) {
+        self.add(name);
+        let mut layers = self.0.lock().unwrap();
+        if let Some(layer) = layers.iter_mut().find(|layer| layer.name == name) {
+            layer.visible = visible;
+        }
+    }
+
+    /// Returns `true` if entities in the layer are rendered.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn is_visible(&self, name: &str) -> bool {
+        let layers = self.0.lock().unwrap();
+        !matches!(Self::find(&layers, name), Some((_, false)))
+    }
+
+    /// Returns the position of the layer, `0` being the bottom, if it is part of the set.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .position(|layer| layer.name == name)
+    }
+
+    /// Returns the names of the layers, from the bottom to the top.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|layer| layer.name.clone())
+            .collect()
+    }
+
+    // Copy of the current layers, to sort entities without holding the lock.
+    pub(crate) fn snapshot(&self) -> Vec<Layer> {
+        self.0.lock().unwrap().clone()
+    }
+
+    // Position and visibility of the layer entities with the given layer name are rendered in.
+    pub(crate) fn find(layers: &[Layer], name: &str) -> Option<(usize, bool)> {
+        let find = |name: &str| {
+            layers
+                .iter()
+                .position(|layer| layer.name == name)
+                .map(|index| (index, layers[index].visible))
+        };
+        find(name).or_else(|| find(Self::DEFAULT))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Layers;
+
+    #[test]
+    fn layers_order() {
+        let layers = Layers::new();
+        layers.add("ui");
+        layers.add("ui");
+        layers.move_to("background", 0);
+        assert_eq!(layers.names(), ["background", Layers::DEFAULT, "ui"]);
+        layers.move_to("ui", 0);
+        layers.move_to("background", 10);
+        assert_eq!(layers.names(), ["ui", Layers::DEFAULT, "background"]);
+        assert_eq!(layers.position("background"), Some(2));
+        assert!(layers.remove("ui"));
+        assert!(!layers.remove("ui"));
+        assert_eq!(layers.position("ui"), None);
+    }
+
+    #[test]
+    fn layers_find() {
+        let layers = Layers::new();
+        layers.add("ui");
+        layers.set_visible(Layers::DEFAULT, false);
+        let snapshot = layers.snapshot();
+        assert_eq!(Layers::find(&snapshot, "ui"), Some((1, true)));
+        // Unknown layers fall back to the default layer.
+        assert_eq!(Layers::find(&snapshot, "unknown"), Some((0, false)));
+        assert!(!layers.is_visible("unknown"));
+        layers.remove(Layers::DEFAULT);
+        assert_eq!(Layers::find(&layers.snapshot(), "unknown"), None);
+        assert!(layers.is_visible("unknown"));
+    }
+}
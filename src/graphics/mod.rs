@@ -1,8 +1,10 @@
 //! Render functionality.
 mod config;
+mod layers;
 mod renderer;
 
 pub use config::RendererConfig;
+pub use layers::Layers;
 pub use renderer::Renderer;
 
 use crate::error::CtraitResult;
@@ -39,7 +41,7 @@ impl<'a> TextureManager<'a> {
     /// # Errors
     ///
     /// This function will return an error if the texture fails to load.
-    pub fn load(&mut self, path: &str) -> CtraitResult<Rc<Texture>> {
+    pub fn load(&mut self, path: &str) -> CtraitResult<Rc<Texture<'_>>> {
         self.cache.get(path).cloned().map_or_else(
             || {
                 let resource = Rc::new(self.texture_creator.load_texture(path)?);
@@ -59,6 +61,7 @@ pub struct RenderContext<'a> {
     pub canvas: WindowCanvas,
     /// Manager to organize and delegate the game's textures.
     pub texture_manager: TextureManager<'a>,
+    alpha: f32,
 }
 
 impl<'a> RenderContext<'a> {
@@ -66,6 +69,24 @@ impl<'a> RenderContext<'a> {
         Self {
             canvas,
             texture_manager,
+            alpha: 1.0,
         }
     }
+
+    /// Interpolation factor between the previous and the current fixed update state.
+    ///
+    /// The value lies in `[0, 1)` and represents how far the current frame is between two
+    /// [`FixedUpdate::fixed_update`](crate::traits::FixedUpdate::fixed_update) calls.
+    /// [`Renderable`](crate::traits::Renderable) entities can use it to blend between their
+    /// previous and current state for smooth rendering. When fixed updates run on a separate
+    /// thread ([`FixedUpdateMode::Threaded`](crate::game::FixedUpdateMode::Threaded)), this is
+    /// always `1.0`.
+    #[must_use]
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub(crate) fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
 }
@@ -1,10 +1,19 @@
 use crate::{
     camera::Camera,
-    entity::{Entities, Entity},
-    graphics::{RenderContext, RendererConfig},
-    traits::{Interactive, Renderable},
+    entity::{self, Entities, Entity},
+    game::{FrameStats, Isolation},
+    graphics::{layers::Layer, Layers, RenderContext, RendererConfig},
+    traits::{Renderable, TypeName},
+};
+use sdl2::{
+    self,
+    event::{Event, WindowEvent},
+    pixels::Color,
+};
+use std::{
+    sync::{PoisonError, Weak},
+    time::{Duration, Instant},
 };
-use sdl2::{self, event::Event, pixels::Color, EventPump};
 
 /// Renders entities.
 #[derive(Debug)]
@@ -12,7 +21,10 @@ pub struct Renderer {
     /// The renderer's current configuration.
     pub config: RendererConfig,
     quit: bool,
+    focused: bool,
+    minimized: bool,
     camera: Option<Entity<Camera>>,
+    layers: Layers,
 }
 
 impl Default for Renderer {
@@ -48,7 +60,10 @@ impl Renderer {
         Self {
             config,
             quit: false,
+            focused: true,
+            minimized: false,
             camera: None,
+            layers: Layers::new(),
         }
     }
 
@@ -78,7 +93,7 @@ impl Renderer {
     /// use ctrait::{camera::Camera, entity, entity::Entity, graphics::Renderer, math::Vector2};
     ///
     /// fn increment_camera_x(camera: Entity<Camera>) {
-    ///     camera.lock().unwrap().position.x += 1.0;
+    ///     camera.write().unwrap().position.x += 1.0;
     /// }
     ///
     /// let camera = entity!(Camera::new(Vector2::repeat(0.0)));
@@ -87,7 +102,7 @@ impl Renderer {
     /// increment_camera_x(Entity::clone(&camera));
     /// increment_camera_x(Entity::clone(&camera));
     ///
-    /// assert_eq!(camera.lock().unwrap().position.x, 2.0);
+    /// assert_eq!(camera.read().unwrap().position.x, 2.0);
     ///
     /// // There is no need to clone camera here because it is not being used after this point.
     /// let renderer = Renderer::default()
@@ -99,58 +114,188 @@ impl Renderer {
         self
     }
 
+    /// Use the given render layers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::graphics::{Layers, Renderer};
+    ///
+    /// let layers = Layers::new();
+    /// layers.move_to("background", 0);
+    /// let renderer = Renderer::default().with_layers(layers);
+    /// ```
+    #[must_use]
+    pub fn with_layers(mut self, layers: Layers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Returns a handle to the renderer's layers, which can be changed at runtime.
+    ///
+    /// See [`Layers`].
+    #[must_use]
+    pub fn layers(&self) -> Layers {
+        Layers::clone(&self.layers)
+    }
+
     // Check if quit has been requested.
     pub(crate) fn has_quit(&self) -> bool {
         self.quit
     }
 
-    // Poll for pending events. Will mark quit as true if quit event was received.
-    pub(crate) fn process_event(
-        &mut self,
-        event_pump: &mut EventPump,
-        entities: &mut Entities<dyn Interactive>,
-    ) {
-        let entities = entities.access();
-        for event in event_pump.poll_iter() {
-            if let Event::Quit { .. } = event {
-                self.quit = true;
-                break;
-            }
-            entities
-                .lock()
-                .unwrap()
-                .iter_mut()
-                .for_each(|entity| entity.upgrade().unwrap().lock().unwrap().on_event(&event));
+    // Frame rate limit depending on whether the window is in the background.
+    pub(crate) fn target_fps(&self) -> Option<u32> {
+        self.config.frame_rate(!self.focused || self.minimized)
+    }
+
+    // Handle a polled event. Will mark quit as true if quit event was received.
+    pub(crate) fn process_event(&mut self, event: &Event) {
+        match event {
+            Event::Quit { .. } => self.quit = true,
+            Event::Window { win_event, .. } => match win_event {
+                WindowEvent::FocusGained => self.focused = true,
+                WindowEvent::FocusLost => self.focused = false,
+                WindowEvent::Minimized => self.minimized = true,
+                WindowEvent::Restored | WindowEvent::Maximized => self.minimized = false,
+                _ => {}
+            },
+            _ => {}
         }
     }
 
-    // Render a vector of Rederable objects to canvas.
+    // Update the camera and return a copy of it. The camera is not locked while entities are, as
+    // entities can lock the camera from their updates, and locking them in both orders is
+    // reported by the deadlock diagnostics.
+    fn camera_snapshot(&self, update: impl FnOnce(&mut Camera)) -> Option<Camera> {
+        let mut camera = entity::write(self.camera.as_ref()?);
+        update(&mut camera);
+        Some(*camera)
+    }
+
+    // Render the Renderable entities of every container to canvas, sorted by layer and z-index,
+    // followed by the given statistics overlay.
+    // Returns the time spent rendering each type of entity, slowest first.
     pub(crate) fn render(
         &mut self,
         context: &mut RenderContext,
-        entities: &mut Entities<dyn Renderable>,
-    ) {
-        if let Some(camera) = &mut self.camera {
-            let mut camera = camera.lock().unwrap();
-            camera.update(&context.canvas);
+        containers: &[Entities<dyn Renderable>],
+        overlay: Option<&FrameStats>,
+        isolation: &Isolation,
+    ) -> Vec<(&'static str, Duration)> {
+        let mut render_times: Vec<(&'static str, Duration)> = Vec::new();
+        if let Some(camera) = self.camera_snapshot(|camera| camera.update(&context.canvas)) {
             context.canvas.set_draw_color(Color::BLACK);
             context.canvas.clear();
-            for entity in entities.access().lock().unwrap().iter() {
-                entity
-                    .upgrade()
-                    .unwrap()
-                    .lock()
-                    .unwrap()
-                    .render(&camera, context);
+            for entity in sorted(containers, &self.layers.snapshot()) {
+                isolation.call_read(&entity, |entity| {
+                    let instant = Instant::now();
+                    let result = entity.render(&camera, context);
+                    let elapsed = instant.elapsed();
+                    let name = TypeName::type_name(entity);
+                    match render_times
+                        .iter_mut()
+                        .find(|(type_name, _)| *type_name == name)
+                    {
+                        Some((_, time)) => *time += elapsed,
+                        None => render_times.push((name, elapsed)),
+                    }
+                    result
+                });
+            }
+            if let Some(stats) = overlay {
+                // Errors are reported like errors of entities.
+                isolation.call_fn(std::any::type_name::<FrameStats>(), "render", || {
+                    stats.draw_overlay(&mut context.canvas)
+                });
             }
             context.canvas.present();
         }
+        render_times.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+        render_times
     }
 }
 
+// Active and visible entities of every container, from the bottom to the top.
+// The sort is stable, so entities in the same layer and with the same z-index keep the order of
+// the containers and the order they were added in.
+fn sorted(
+    containers: &[Entities<dyn Renderable>],
+    layers: &[Layer],
+) -> Vec<Entity<dyn Renderable>> {
+    let mut entities: Vec<_> = containers
+        .iter()
+        .flat_map(|entities| {
+            entities
+                .access()
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        })
+        .filter_map(|entity| {
+            let (active, layer, z_index) = {
+                let entity = entity::read(&entity);
+                let layer = Layers::find(layers, entity.layer());
+                (entity.render_active(), layer, entity.z_index())
+            };
+            match layer {
+                _ if !active => None,
+                Some((_, false)) => None,
+                Some((position, true)) => Some(((position, z_index), entity)),
+                None => Some(((layers.len(), z_index), entity)),
+            }
+        })
+        .collect();
+    entities.sort_by_key(|(key, _)| *key);
+    entities.into_iter().map(|(_, entity)| entity).collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Camera, Renderer};
+    use super::{
+        sorted, Camera, Entities, Entity, Event, Layers, RenderContext, Renderable, Renderer,
+        RendererConfig, WindowEvent,
+    };
+    use crate::{deadlock, entity, error::CtraitResult, game::Game, traits::Update};
+    use std::{cell::RefCell, collections::HashMap, sync::Arc, time::Duration};
+
+    struct Layered(&'static str, i32);
+    impl Renderable for Layered {
+        fn render(&self, _: &Camera, _: &mut RenderContext) -> CtraitResult<()> {
+            Ok(())
+        }
+
+        fn layer(&self) -> &str {
+            self.0
+        }
+
+        fn z_index(&self) -> i32 {
+            self.1
+        }
+    }
+
+    thread_local! {
+        // Names of the entities created by layered, keyed by address.
+        static NAMES: RefCell<HashMap<*const (), &'static str>> = RefCell::default();
+    }
+
+    fn layered(name: &'static str, layer: &'static str, z_index: i32) -> Entity<dyn Renderable> {
+        let entity: Entity<dyn Renderable> = crate::entity!(Layered(layer, z_index));
+        NAMES.with(|names| names.borrow_mut().insert(entity::address(&entity), name));
+        entity
+    }
+
+    fn names(entities: &[Entity<dyn Renderable>]) -> Vec<&'static str> {
+        NAMES.with(|names| {
+            let names = names.borrow();
+            entities
+                .iter()
+                .map(|entity| names[&entity::address(entity)])
+                .collect()
+        })
+    }
 
     #[test]
     fn renderer_with_camera() {
@@ -158,6 +303,139 @@ mod tests {
         assert!(renderer.camera.is_some());
     }
 
+    #[test]
+    fn renderer_target_fps() {
+        let mut renderer = Renderer::new(RendererConfig {
+            target_fps: Some(60),
+            background_fps: Some(5),
+            ..RendererConfig::default()
+        });
+        let window_event = |win_event| Event::Window {
+            timestamp: 0,
+            window_id: 0,
+            win_event,
+        };
+        assert_eq!(renderer.target_fps(), Some(60));
+        renderer.process_event(&window_event(WindowEvent::FocusLost));
+        assert_eq!(renderer.target_fps(), Some(5));
+        renderer.process_event(&window_event(WindowEvent::FocusGained));
+        renderer.process_event(&window_event(WindowEvent::Minimized));
+        assert_eq!(renderer.target_fps(), Some(5));
+        renderer.process_event(&window_event(WindowEvent::Restored));
+        assert_eq!(renderer.target_fps(), Some(60));
+    }
+
+    #[test]
+    fn renderer_camera_lock_order() {
+        struct Ball {
+            camera: Entity<Camera>,
+        }
+        impl Update for Ball {
+            fn update(&mut self, _: f32) -> CtraitResult<()> {
+                let _ = entity::read(&self.camera).position;
+                Ok(())
+            }
+        }
+        impl Renderable for Ball {
+            fn render(&self, _: &Camera, _: &mut RenderContext) -> CtraitResult<()> {
+                Ok(())
+            }
+        }
+
+        deadlock::enable(Duration::from_secs(60));
+        let camera = crate::entity!(Camera::default());
+        let renderer = Renderer::default().with_camera_entity(Entity::clone(&camera));
+        let ball = crate::entity!(Ball {
+            camera: Entity::clone(&camera),
+        });
+        let mut game = Game::new();
+        game.update_entities
+            .add_entities(&[Entity::clone(&ball) as Entity<dyn Update>]);
+        let mut containers = [Entities::new()];
+        containers[0].add_entities(&[Entity::clone(&ball) as Entity<dyn Renderable>]);
+        // The ball locks the camera while locked by its update.
+        game.step(0.0);
+        // Rendering locks the camera, then the entities.
+        assert!(renderer.camera_snapshot(|_| {}).is_some());
+        for entity in sorted(&containers, &Layers::new().snapshot()) {
+            drop(entity::read(&entity));
+        }
+        let (camera, ball) = (entity::address(&camera), entity::address(&ball));
+        assert!(deadlock::is_ordered(ball, camera));
+        assert!(!deadlock::is_ordered(camera, ball));
+        deadlock::disable();
+    }
+
+    #[test]
+    fn renderer_sorted() {
+        let player = layered("player", Layers::DEFAULT, 1);
+        let shadow = layered("shadow", Layers::DEFAULT, 0);
+        let background = layered("background", "background", 5);
+        let enemy = layered("enemy", "unknown", 1);
+        let menu = layered("menu", "ui", 0);
+        let game_entities: [Entity<dyn Renderable>; 3] = [player, shadow, background];
+        let scene_entities: [Entity<dyn Renderable>; 2] = [enemy, menu];
+        let mut containers = [Entities::new(), Entities::new()];
+        containers[0].add_entities(&game_entities);
+        containers[1].add_entities(&scene_entities);
+
+        let layers = Layers::new();
+        layers.move_to("background", 0);
+        layers.add("ui");
+        assert_eq!(
+            names(&sorted(&containers, &layers.snapshot())),
+            ["background", "shadow", "player", "enemy", "menu"]
+        );
+
+        layers.set_visible("ui", false);
+        layers.move_to("background", 10);
+        assert_eq!(
+            names(&sorted(&containers, &layers.snapshot())),
+            ["shadow", "player", "enemy", "background"]
+        );
+    }
+
+    #[test]
+    fn renderer_sorted_inactive() {
+        struct Hidden;
+        impl Renderable for Hidden {
+            fn render(&self, _: &Camera, _: &mut RenderContext) -> CtraitResult<()> {
+                Ok(())
+            }
+
+            fn render_active(&self) -> bool {
+                false
+            }
+        }
+        let entities: [Entity<dyn Renderable>; 2] = [
+            crate::entity!(Hidden),
+            layered("player", Layers::DEFAULT, 0),
+        ];
+        let mut containers = [Entities::new()];
+        containers[0].add_entities(&entities);
+        assert_eq!(
+            names(&sorted(&containers, &Layers::new().snapshot())),
+            ["player"]
+        );
+    }
+
+    #[test]
+    fn renderer_sorted_poisoned() {
+        let player = layered("player", Layers::DEFAULT, 0);
+        let mut containers = [Entities::new()];
+        containers[0].add_entities(&[Entity::clone(&player)]);
+        let access = Arc::clone(containers[0].access());
+        let _ = std::thread::spawn(move || {
+            let _guard = access.lock().unwrap();
+            panic!("poison");
+        })
+        .join();
+        assert_eq!(
+            names(&sorted(&containers, &Layers::new().snapshot())),
+            ["player"]
+        );
+    }
+
     #[test]
     fn renderer_with_camera_entity() {
         let camera = crate::entity!(Camera::default());
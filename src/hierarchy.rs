@@ -0,0 +1,471 @@
+//! Parent/child relationships between entities.
+
+use crate::{
+    entity::{self, Entity},
+    error::{CtraitError, CtraitResult},
+    game::Isolation,
+    traits::Spatial,
+    transform::Transform,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock, Weak},
+};
+
+// An entity of the hierarchy, keyed by its address.
+struct Node {
+    entity: Weak<RwLock<dyn Spatial>>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+#[derive(Default)]
+struct Nodes(HashMap<usize, Node>);
+
+impl Nodes {
+    fn insert(&mut self, entity: &Entity<dyn Spatial>) -> usize {
+        let address = entity::address(entity) as usize;
+        // The address might be reused from a dropped entity.
+        if self
+            .0
+            .get(&address)
+            .is_some_and(|node| node.entity.strong_count() == 0)
+        {
+            self.remove(address);
+        }
+        self.0.entry(address).or_insert_with(|| Node {
+            entity: Arc::downgrade(entity),
+            parent: None,
+            children: Vec::new(),
+        });
+        address
+    }
+
+    // Detach the node from its parent, making it a root.
+    fn detach(&mut self, address: usize) {
+        if let Some(parent) = self.0.get_mut(&address).and_then(|node| node.parent.take()) {
+            if let Some(parent) = self.0.get_mut(&parent) {
+                parent.children.retain(|&child| child != address);
+            }
+            self.forget(parent);
+        }
+    }
+
+    // Remove the node if it has no relationship left.
+    fn forget(&mut self, address: usize) {
+        if let Some(node) = self.0.get(&address) {
+            if node.parent.is_none() && node.children.is_empty() {
+                self.0.remove(&address);
+            }
+        }
+    }
+
+    // Returns `true` if `ancestor` is `address` or one of its ancestors.
+    fn is_ancestor(&self, ancestor: usize, mut address: usize) -> bool {
+        loop {
+            if address == ancestor {
+                return true;
+            }
+            match self.0.get(&address).and_then(|node| node.parent) {
+                Some(parent) => address = parent,
+                None => return false,
+            }
+        }
+    }
+
+    fn children(&self, address: usize) -> Vec<Entity<dyn Spatial>> {
+        self.0
+            .get(&address)
+            .map(|node| {
+                node.children
+                    .iter()
+                    .filter_map(|child| self.0.get(child)?.entity.upgrade())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Remove the nodes of dropped entities. Their children become roots.
+    fn prune(&mut self) {
+        let dropped: Vec<usize> = self
+            .0
+            .iter()
+            .filter(|(_, node)| node.entity.strong_count() == 0)
+            .map(|(&address, _)| address)
+            .collect();
+        for address in dropped {
+            self.remove(address);
+        }
+    }
+
+    // Remove the node, making its children roots.
+    fn remove(&mut self, address: usize) {
+        self.detach(address);
+        if let Some(node) = self.0.remove(&address) {
+            for child in node.children {
+                if let Some(node) = self.0.get_mut(&child) {
+                    node.parent = None;
+                }
+                self.forget(child);
+            }
+        }
+    }
+}
+
+/// Parent/child relationships between [`Spatial`] entities.
+///
+/// The world transform of an entity with a parent is the world transform of its parent combined
+/// with its own [`Spatial::local_transform`]. Every frame, after the late updates (see the
+/// [phase order](crate::game::Game#phase-order)), the game propagates the world transforms from
+/// the roots of the hierarchy to their descendants, and notifies each entity of the hierarchy
+/// through [`Spatial::set_world_transform`]. Panics of these calls are handled according to the
+/// game's [`PanicPolicy`](crate::game::PanicPolicy). Despawning an entity with
+/// [`Game::despawn`](crate::game::Game::despawn) despawns its descendants as well.
+///
+/// The game owns a hierarchy, see [`Game::hierarchy`](crate::game::Game::hierarchy). The handle
+/// can be cloned and stored in entities. Entities are referred to weakly: once dropped, an
+/// entity leaves the hierarchy and its children become roots.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{entity, entity::Entity, game::Game, traits::Spatial, transform::Transform};
+/// use ctrait::math::Vector2;
+///
+/// struct Body {
+///     local: Transform,
+///     world: Transform,
+/// }
+///
+/// impl Spatial for Body {
+///     fn local_transform(&self) -> Transform {
+///         self.local
+///     }
+///
+///     fn set_world_transform(&mut self, transform: Transform) {
+///         self.world = transform;
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let player: Entity<dyn Spatial> = entity!(Body {
+///     local: Transform::new(Vector2::new(10.0, 0.0)),
+///     world: Transform::default(),
+/// });
+/// let weapon = entity!(Body {
+///     local: Transform::new(Vector2::new(2.0, 0.0)),
+///     world: Transform::default(),
+/// });
+///
+/// let mut game = Game::new();
+/// game.hierarchy().set_parent(&(Entity::clone(&weapon) as Entity<dyn Spatial>), &player)?;
+/// game.step(0.0);
+/// assert_eq!(weapon.read().unwrap().world.position, Vector2::new(12.0, 0.0));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Hierarchy(Arc<Mutex<Nodes>>);
+
+impl Hierarchy {
+    /// Make `parent` the parent of `child`, replacing its previous parent.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if `parent` is `child` or one of its descendants.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn set_parent(
+        &self,
+        child: &Entity<dyn Spatial>,
+        parent: &Entity<dyn Spatial>,
+    ) -> CtraitResult<()> {
+        let mut nodes = self.0.lock().unwrap();
+        let child_address = entity::address(child) as usize;
+        let parent_address = entity::address(parent) as usize;
+        if nodes.is_ancestor(child_address, parent_address) {
+            return Err(CtraitError::Other(String::from(
+                "an entity cannot be the parent of one of its ancestors",
+            )));
+        }
+        let child = nodes.insert(child);
+        let parent = nodes.insert(parent);
+        nodes.detach(child);
+        nodes.0.get_mut(&child).unwrap().parent = Some(parent);
+        nodes.0.get_mut(&parent).unwrap().children.push(child);
+        Ok(())
+    }
+
+    /// Detach the entity from its parent, making it a root.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn remove_parent<T: ?Sized>(&self, child: &Entity<T>) {
+        let address = entity::address(child) as usize;
+        let mut nodes = self.0.lock().unwrap();
+        nodes.detach(address);
+        nodes.forget(address);
+    }
+
+    /// Returns the parent of the entity, if it has one.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn parent<T: ?Sized>(&self, entity: &Entity<T>) -> Option<Entity<dyn Spatial>> {
+        let nodes = self.0.lock().unwrap();
+        let parent = nodes.0.get(&(entity::address(entity) as usize))?.parent?;
+        nodes.0.get(&parent)?.entity.upgrade()
+    }
+
+    /// Returns the children of the entity, in the order they were attached.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn children<T: ?Sized>(&self, entity: &Entity<T>) -> Vec<Entity<dyn Spatial>> {
+        self.0
+            .lock()
+            .unwrap()
+            .children(entity::address(entity) as usize)
+    }
+
+    /// Computes the current world transform of the entity from its parent chain.
+    ///
+    /// Unlike the transform passed to [`Spatial::set_world_transform`], this reflects changes
+    /// made since the last propagation.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle or of the entities panics.
+    #[must_use]
+    pub fn world_transform(&self, entity: &Entity<dyn Spatial>) -> Transform {
+        let mut transform = entity::read(entity).local_transform();
+        let mut current = self.parent(entity);
+        while let Some(parent) = current {
+            transform = entity::read(&parent).local_transform() * transform;
+            current = self.parent(&parent);
+        }
+        transform
+    }
+
+    // Propagate world transforms from the roots to their descendants. Panics of the entities are
+    // handled by the isolation, in which case their descendants are placed relative to their
+    // parent.
+    pub(crate) fn propagate(&self, isolation: &Isolation) {
+        // Collect the entities while holding the lock, but lock the entities without holding it,
+        // so entities can use the hierarchy when notified.
+        let order = {
+            let mut nodes = self.0.lock().unwrap();
+            nodes.prune();
+            let mut order = Vec::new();
+            let mut stack: Vec<(usize, Option<usize>)> = nodes
+                .0
+                .iter()
+                .filter(|(_, node)| node.parent.is_none())
+                .map(|(&address, _)| (address, None))
+                .collect();
+            while let Some((address, parent)) = stack.pop() {
+                let node = &nodes.0[&address];
+                if let Some(entity) = node.entity.upgrade() {
+                    let index = order.len();
+                    order.push((entity, parent));
+                    stack.extend(
+                        node.children
+                            .iter()
+                            .rev()
+                            .map(|&child| (child, Some(index))),
+                    );
+                }
+            }
+            order
+        };
+        let mut transforms: Vec<Transform> = Vec::with_capacity(order.len());
+        for (entity, parent) in order {
+            let parent = parent.map(|parent| transforms[parent]);
+            let mut world = None;
+            isolation.call(&entity, |entity| {
+                let local = entity.local_transform();
+                let transform = parent.map_or(local, |parent| parent * local);
+                entity.set_world_transform(transform);
+                world = Some(transform);
+                Ok(())
+            });
+            transforms.push(world.or(parent).unwrap_or_default());
+        }
+    }
+
+    // Remove the entity with the given address from the hierarchy, returning its children.
+    pub(crate) fn remove_address(&self, address: *const ()) -> Vec<Entity<dyn Spatial>> {
+        let mut nodes = self.0.lock().unwrap();
+        let children = nodes.children(address as usize);
+        nodes.remove(address as usize);
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entity, Hierarchy, Spatial, Transform};
+    use crate::{
+        command::Commands,
+        game::{Isolation, PanicPolicy},
+        math::Vector2,
+    };
+    use std::sync::Arc;
+
+    struct Node {
+        local: Transform,
+        world: Option<Transform>,
+    }
+
+    impl Spatial for Node {
+        fn local_transform(&self) -> Transform {
+            self.local
+        }
+
+        fn set_world_transform(&mut self, transform: Transform) {
+            self.world = Some(transform);
+        }
+    }
+
+    fn node(x: f32) -> Entity<Node> {
+        crate::entity!(Node {
+            local: Transform::new(Vector2::new(x, 0.0)),
+            world: None,
+        })
+    }
+
+    fn spatial(entity: &Entity<Node>) -> Entity<dyn Spatial> {
+        Entity::<Node>::clone(entity)
+    }
+
+    fn isolation(policy: PanicPolicy) -> Isolation {
+        Isolation {
+            policy,
+            commands: Commands::default(),
+        }
+    }
+
+    #[test]
+    fn hierarchy_set_parent() {
+        let hierarchy = Hierarchy::default();
+        let (a, b, c) = (
+            spatial(&node(1.0)),
+            spatial(&node(2.0)),
+            spatial(&node(3.0)),
+        );
+        hierarchy.set_parent(&b, &a).unwrap();
+        hierarchy.set_parent(&c, &b).unwrap();
+        assert!(hierarchy.set_parent(&a, &c).is_err());
+        assert!(hierarchy.set_parent(&a, &a).is_err());
+        assert_eq!(hierarchy.children(&a).len(), 1);
+        // Reparenting detaches the child from its previous parent.
+        hierarchy.set_parent(&c, &a).unwrap();
+        assert!(hierarchy.children(&b).is_empty());
+        assert_eq!(hierarchy.children(&a).len(), 2);
+        hierarchy.remove_parent(&c);
+        assert!(hierarchy.parent(&c).is_none());
+        assert_eq!(
+            hierarchy.world_transform(&b).position,
+            Vector2::new(3.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn hierarchy_propagate() {
+        let hierarchy = Hierarchy::default();
+        let (a, b, c) = (node(1.0), node(2.0), node(3.0));
+        let d = node(4.0);
+        hierarchy.set_parent(&spatial(&c), &spatial(&b)).unwrap();
+        hierarchy.set_parent(&spatial(&b), &spatial(&a)).unwrap();
+        hierarchy.set_parent(&spatial(&d), &spatial(&a)).unwrap();
+        hierarchy.propagate(&isolation(PanicPolicy::Abort));
+        let world = |entity: &Entity<Node>| entity.read().unwrap().world.unwrap().position.x;
+        assert!((world(&a) - 1.0).abs() < f32::EPSILON);
+        assert!((world(&b) - 3.0).abs() < f32::EPSILON);
+        assert!((world(&c) - 6.0).abs() < f32::EPSILON);
+        assert!((world(&d) - 5.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn hierarchy_propagate_panic() {
+        struct Faulty;
+
+        impl Spatial for Faulty {
+            fn local_transform(&self) -> Transform {
+                panic!("local transform failed");
+            }
+
+            fn set_world_transform(&mut self, _: Transform) {}
+        }
+
+        let hierarchy = Hierarchy::default();
+        let (a, c) = (node(1.0), node(3.0));
+        let b: Entity<dyn Spatial> = crate::entity!(Faulty);
+        hierarchy.set_parent(&b, &spatial(&a)).unwrap();
+        hierarchy.set_parent(&spatial(&c), &b).unwrap();
+        let isolation = isolation(PanicPolicy::Remove);
+        hierarchy.propagate(&isolation);
+        // The child of the entity which panicked is placed relative to its grandparent.
+        let x = c.read().unwrap().world.unwrap().position.x;
+        assert!((x - 4.0).abs() < f32::EPSILON);
+        // The entity which panicked is despawned at the end of the phase.
+        assert_eq!(isolation.commands.take().len(), 1);
+    }
+
+    #[test]
+    fn hierarchy_reused_address() {
+        let hierarchy = Hierarchy::default();
+        let (a, b) = (node(1.0), node(2.0));
+        // A node left by a dropped entity at the address of a.
+        let dropped = spatial(&node(0.0));
+        let weak = Arc::downgrade(&dropped);
+        drop(dropped);
+        hierarchy.0.lock().unwrap().0.insert(
+            crate::entity::address(&a) as usize,
+            super::Node {
+                entity: weak,
+                parent: None,
+                children: Vec::new(),
+            },
+        );
+        hierarchy.set_parent(&spatial(&b), &spatial(&a)).unwrap();
+        hierarchy.propagate(&isolation(PanicPolicy::Abort));
+        assert!(hierarchy.parent(&spatial(&b)).is_some());
+        let x = b.read().unwrap().world.unwrap().position.x;
+        assert!((x - 3.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn hierarchy_remove_address() {
+        let hierarchy = Hierarchy::default();
+        let (a, b, c) = (
+            spatial(&node(1.0)),
+            spatial(&node(2.0)),
+            spatial(&node(3.0)),
+        );
+        hierarchy.set_parent(&b, &a).unwrap();
+        hierarchy.set_parent(&c, &b).unwrap();
+        let children = hierarchy.remove_address(crate::entity::address(&a));
+        assert_eq!(children.len(), 1);
+        assert!(hierarchy.parent(&b).is_none());
+        assert_eq!(hierarchy.children(&b).len(), 1);
+    }
+
+    #[test]
+    fn hierarchy_prune() {
+        let hierarchy = Hierarchy::default();
+        let (a, b) = (spatial(&node(1.0)), spatial(&node(2.0)));
+        hierarchy.set_parent(&b, &a).unwrap();
+        drop(a);
+        hierarchy.propagate(&isolation(PanicPolicy::Abort));
+        assert!(hierarchy.parent(&b).is_none());
+        assert!(hierarchy.0.lock().unwrap().0.is_empty());
+    }
+}
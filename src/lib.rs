@@ -18,6 +18,7 @@
 //! use ctrait::{
 //!     camera::Camera,
 //!     entity, entities,
+//!     error::CtraitResult,
 //!     game::Game,
 //!     graphics::{RenderContext, Renderer},
 //!     rect::Rect,
@@ -41,9 +42,9 @@
 //!
 //! // Allow the player to be rendered.
 //! impl Renderable for Player {
-//!     fn render(&self, camera: &Camera, context: &mut RenderContext) {
+//!     fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
 //!         // Since Rect implements Renderable, it can be rendered with a single function call.
-//!         self.rect.render(camera, context);
+//!         self.rect.render(camera, context)
 //!     }
 //! }
 //!
@@ -61,17 +62,26 @@
 //! # }
 //! ```
 
+pub mod bus;
 pub mod camera;
+pub mod command;
+pub mod deadlock;
+pub mod ecs;
 pub mod entity;
 pub mod error;
 pub mod game;
 pub mod graphics;
+pub mod hierarchy;
 pub mod rect;
+pub mod registry;
+pub mod scene;
+pub mod scheduler;
 pub mod sprite;
 pub mod tile;
 pub mod traits;
+pub mod transform;
 
 pub use nalgebra as math;
 
 // Re-export.
-pub use sdl2::{event::Event, keyboard::Keycode, pixels::Color};
+pub use sdl2::{event::Event, keyboard, keyboard::Keycode, pixels::Color};
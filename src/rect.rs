@@ -1,6 +1,9 @@
 //! Rectangle.
 
-use crate::{camera::Camera, graphics::RenderContext, math::Vector2, traits::Renderable};
+use crate::{
+    camera::Camera, error::CtraitResult, graphics::RenderContext, math::Vector2,
+    traits::Renderable, transform::Transform,
+};
 use sdl2::{pixels::Color, rect::Rect as CanvasRect};
 
 /// A rectangle relative to world coordinates.
@@ -146,6 +149,30 @@ impl Rect {
             && self.position.y + self.size.y > other.position.y
     }
 
+    /// Returns the rectangle, relative to the given transform, relative to the transform's own
+    /// reference instead.
+    ///
+    /// This is useful to render a rectangle relative to the world transform of an entity, see
+    /// [`Spatial`](crate::traits::Spatial).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{math::Vector2, rect::Rect, transform::Transform};
+    ///
+    /// let transform = Transform::new(Vector2::new(10.0, 0.0)).with_scale(Vector2::repeat(2.0));
+    /// let rect = Rect::new(1.0, 1.0, 5.0, 5.0).transformed(&transform);
+    /// assert_eq!(rect, Rect::new(12.0, 2.0, 10.0, 10.0));
+    /// ```
+    #[must_use]
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Self {
+            position: transform.transform_point(self.position),
+            size: self.size.component_mul(&transform.scale),
+            color: self.color,
+        }
+    }
+
     // Retrieves the equivalent CanvasRect relative to camera.
     // Will return None if the CanvasRect is outside of the camera's view.
     pub(crate) fn as_canvas_rect(&self, camera: &Camera) -> Option<CanvasRect> {
@@ -168,15 +195,16 @@ impl Rect {
 
 impl Renderable for Rect {
     #[track_caller]
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
         if let Some(color) = self.color {
             if let Some(canvas_rect) = self.as_canvas_rect(camera) {
                 context.canvas.set_draw_color(color);
-                context.canvas.fill_rect(canvas_rect).unwrap();
+                context.canvas.fill_rect(canvas_rect)?;
             }
         } else {
             panic!("Rect must have defined color to be rendered");
         }
+        Ok(())
     }
 }
 
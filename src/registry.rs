@@ -0,0 +1,375 @@
+//! Names and tags of entities.
+
+use crate::{
+    entity::{self, Entity},
+    error::{CtraitError, CtraitResult},
+};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, Weak},
+};
+
+// Names and tags of an entity, keyed by its address.
+#[derive(Debug)]
+struct Entry {
+    entity: Weak<dyn Any + Send + Sync>,
+    name: Option<String>,
+    tags: Vec<String>,
+}
+
+impl Entry {
+    fn get<T: Send + Sync + 'static>(&self) -> Option<Entity<T>> {
+        self.entity.upgrade()?.downcast().ok()
+    }
+
+    fn is_alive(&self) -> bool {
+        self.entity.strong_count() > 0
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<usize, Entry>,
+    names: HashMap<String, usize>,
+    // Addresses of the entities with each tag.
+    tags: HashMap<String, HashSet<usize>>,
+    // Number of entries after the entries of dropped entities were last removed.
+    pruned: usize,
+}
+
+impl Entries {
+    fn entry<T: Send + Sync + 'static>(&mut self, entity: &Entity<T>) -> &mut Entry {
+        let address = entity::address(entity) as usize;
+        // The address might be reused from a dropped entity.
+        if self
+            .entries
+            .get(&address)
+            .is_some_and(|entry| !entry.is_alive())
+        {
+            self.remove(address);
+        }
+        // Dropped entities are only found by sweeping every entry, so the sweep waits until the
+        // number of entries doubled since the last one, keeping its cost amortized.
+        if self.entries.len() >= self.pruned * 2 && !self.entries.contains_key(&address) {
+            self.prune();
+        }
+        let weak: Weak<dyn Any + Send + Sync> = Arc::downgrade(entity) as Weak<_>;
+        self.entries.entry(address).or_insert_with(|| Entry {
+            entity: weak,
+            name: None,
+            tags: Vec::new(),
+        })
+    }
+
+    // Remove the entry if it holds neither a name nor a tag.
+    fn forget(&mut self, address: usize) {
+        if let Some(entry) = self.entries.get(&address) {
+            if entry.name.is_none() && entry.tags.is_empty() {
+                self.entries.remove(&address);
+            }
+        }
+    }
+
+    fn remove(&mut self, address: usize) {
+        let Some(entry) = self.entries.remove(&address) else {
+            return;
+        };
+        if let Some(name) = entry.name {
+            self.names.remove(&name);
+        }
+        for tag in entry.tags {
+            self.untag(address, &tag);
+        }
+    }
+
+    // Remove the address from the index of the tag.
+    fn untag(&mut self, address: usize, tag: &str) {
+        if let Some(addresses) = self.tags.get_mut(tag) {
+            addresses.remove(&address);
+            if addresses.is_empty() {
+                self.tags.remove(tag);
+            }
+        }
+    }
+
+    // Remove the entries of dropped entities.
+    fn prune(&mut self) {
+        let dropped: Vec<usize> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_alive())
+            .map(|(&address, _)| address)
+            .collect();
+        for address in dropped {
+            self.remove(address);
+        }
+        self.pruned = self.entries.len();
+    }
+}
+
+/// Registry of entity names and tags, to find entities from anywhere without holding them.
+///
+/// A name refers to at most one entity and an entity has at most one name, while any number of
+/// entities can share a tag. Entities are registered with their concrete type, which they are
+/// found with: looking an entity up as another type finds nothing.
+///
+/// The game owns a registry, see [`Game::registry`](crate::game::Game::registry). The handle can
+/// be cloned and stored in entities. Entities are referred to weakly: once dropped, or despawned
+/// with [`Game::despawn`](crate::game::Game::despawn), an entity loses its name and tags.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{entity, registry::Registry};
+///
+/// struct Player;
+/// struct Enemy(u32);
+///
+/// let registry = Registry::default();
+/// let player = entity!(Player);
+/// let enemies = [entity!(Enemy(1)), entity!(Enemy(2))];
+/// registry.set_name(&player, "player").unwrap();
+/// for enemy in &enemies {
+///     registry.add_tag(enemy, "enemy");
+/// }
+///
+/// assert!(registry.find_by_name::<Player>("player").is_some());
+/// let health: u32 = registry
+///     .with_tag::<Enemy>("enemy")
+///     .iter()
+///     .map(|enemy| enemy.read().unwrap().0)
+///     .sum();
+/// assert_eq!(health, 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Registry(Arc<Mutex<Entries>>);
+
+impl Registry {
+    /// Name the entity, replacing its previous name.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if another entity already has the name.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn set_name<T: Send + Sync + 'static>(
+        &self,
+        entity: &Entity<T>,
+        name: impl Into<String>,
+    ) -> CtraitResult<()> {
+        let name = name.into();
+        let address = entity::address(entity) as usize;
+        let mut entries = self.0.lock().unwrap();
+        entries.prune();
+        match entries.names.get(&name) {
+            Some(&other) if other != address => {
+                return Err(CtraitError::Other(format!(
+                    "an entity is already named `{}`",
+                    name
+                )));
+            }
+            Some(_) => return Ok(()),
+            None => {}
+        }
+        if let Some(previous) = entries.entry(entity).name.replace(name.clone()) {
+            entries.names.remove(&previous);
+        }
+        entries.names.insert(name, address);
+        Ok(())
+    }
+
+    /// Remove the name of the entity, returning it.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn remove_name<T: ?Sized>(&self, entity: &Entity<T>) -> Option<String> {
+        let address = entity::address(entity) as usize;
+        let mut entries = self.0.lock().unwrap();
+        let name = entries.entries.get_mut(&address)?.name.take()?;
+        entries.names.remove(&name);
+        entries.forget(address);
+        Some(name)
+    }
+
+    /// Returns the name of the entity, if it has one.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn name<T: ?Sized>(&self, entity: &Entity<T>) -> Option<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .entries
+            .get(&(entity::address(entity) as usize))?
+            .name
+            .clone()
+    }
+
+    /// Returns the entity with the given name, if it exists and is of type `T`.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn find_by_name<T: Send + Sync + 'static>(&self, name: &str) -> Option<Entity<T>> {
+        let entries = self.0.lock().unwrap();
+        entries.entries.get(entries.names.get(name)?)?.get()
+    }
+
+    /// Tag the entity. Returns `false` if the entity already had the tag.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn add_tag<T: Send + Sync + 'static>(&self, entity: &Entity<T>, tag: &str) -> bool {
+        let address = entity::address(entity) as usize;
+        let mut entries = self.0.lock().unwrap();
+        let tags = &mut entries.entry(entity).tags;
+        if tags.iter().any(|other| other == tag) {
+            false
+        } else {
+            tags.push(String::from(tag));
+            entries
+                .tags
+                .entry(String::from(tag))
+                .or_default()
+                .insert(address);
+            true
+        }
+    }
+
+    /// Remove the tag from the entity. Returns `false` if the entity did not have the tag.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn remove_tag<T: ?Sized>(&self, entity: &Entity<T>, tag: &str) -> bool {
+        let address = entity::address(entity) as usize;
+        let mut entries = self.0.lock().unwrap();
+        let Some(entry) = entries.entries.get_mut(&address) else {
+            return false;
+        };
+        let len = entry.tags.len();
+        entry.tags.retain(|other| other != tag);
+        let removed = entry.tags.len() != len;
+        entries.untag(address, tag);
+        entries.forget(address);
+        removed
+    }
+
+    /// Returns `true` if the entity has the tag.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn has_tag<T: ?Sized>(&self, entity: &Entity<T>, tag: &str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .entries
+            .get(&(entity::address(entity) as usize))
+            .is_some_and(|entry| entry.tags.iter().any(|other| other == tag))
+    }
+
+    /// Returns the entities of type `T` with the given tag, in no particular order.
+    ///
+    /// Tags are indexed, so the cost of this function depends on the number of entities with the
+    /// tag rather than on the number of registered entities.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    #[must_use]
+    pub fn with_tag<T: Send + Sync + 'static>(&self, tag: &str) -> Vec<Entity<T>> {
+        let entries = self.0.lock().unwrap();
+        entries.tags.get(tag).map_or_else(Vec::new, |addresses| {
+            addresses
+                .iter()
+                .filter_map(|address| entries.entries.get(address)?.get())
+                .collect()
+        })
+    }
+
+    // Remove the names and tags of the entity with the given address.
+    pub(crate) fn remove_address(&self, address: *const ()) {
+        self.0.lock().unwrap().remove(address as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Registry;
+
+    struct Player;
+    struct Enemy;
+
+    #[test]
+    fn registry_names() {
+        let registry = Registry::default();
+        let (a, b) = (crate::entity!(Player), crate::entity!(Player));
+        registry.set_name(&a, "player").unwrap();
+        assert!(registry.set_name(&b, "player").is_err());
+        // Renaming frees the previous name.
+        registry.set_name(&a, "hero").unwrap();
+        registry.set_name(&b, "player").unwrap();
+        assert!(registry.find_by_name::<Player>("hero").is_some());
+        assert!(registry.find_by_name::<Enemy>("hero").is_none());
+        assert_eq!(registry.name(&b).as_deref(), Some("player"));
+        assert_eq!(registry.remove_name(&b).as_deref(), Some("player"));
+        assert!(registry.find_by_name::<Player>("player").is_none());
+        // Dropped entities free their names.
+        drop(a);
+        assert!(registry.find_by_name::<Player>("hero").is_none());
+        registry.set_name(&b, "hero").unwrap();
+    }
+
+    #[test]
+    fn registry_tags() {
+        let registry = Registry::default();
+        let (a, b, c) = (
+            crate::entity!(Enemy),
+            crate::entity!(Enemy),
+            crate::entity!(Player),
+        );
+        assert!(registry.add_tag(&a, "enemy"));
+        assert!(!registry.add_tag(&a, "enemy"));
+        registry.add_tag(&b, "enemy");
+        registry.add_tag(&c, "enemy");
+        assert_eq!(registry.with_tag::<Enemy>("enemy").len(), 2);
+        assert_eq!(registry.with_tag::<Player>("enemy").len(), 1);
+        assert!(registry.remove_tag(&b, "enemy"));
+        assert!(!registry.has_tag(&b, "enemy"));
+        assert_eq!(registry.with_tag::<Enemy>("enemy").len(), 1);
+        registry.remove_address(crate::entity::address(&a));
+        assert!(registry.with_tag::<Enemy>("enemy").is_empty());
+        assert_eq!(registry.0.lock().unwrap().entries.len(), 1);
+        // The tag index only holds tagged entities.
+        registry.remove_tag(&c, "enemy");
+        assert!(registry.0.lock().unwrap().tags.is_empty());
+    }
+
+    #[test]
+    fn registry_tags_dropped() {
+        let registry = Registry::default();
+        let enemies: Vec<_> = (0..100).map(|_| crate::entity!(Enemy)).collect();
+        for enemy in &enemies {
+            registry.add_tag(enemy, "enemy");
+        }
+        drop(enemies);
+        assert!(registry.with_tag::<Enemy>("enemy").is_empty());
+        // Entries of dropped entities are removed as new entities are tagged.
+        let enemies: Vec<_> = (0..100).map(|_| crate::entity!(Enemy)).collect();
+        for enemy in &enemies {
+            registry.add_tag(enemy, "enemy");
+        }
+        assert_eq!(registry.with_tag::<Enemy>("enemy").len(), 100);
+        assert!(registry.0.lock().unwrap().entries.len() < 200);
+    }
+}
@@ -0,0 +1,322 @@
+//! Scenes and the scene stack.
+
+use crate::{
+    entity::Entities,
+    traits::{FixedUpdate, Interactive, LateUpdate, PreUpdate, Renderable, Update},
+};
+use std::sync::{Arc, Mutex};
+
+/// Entity containers owned by a single [`Scene`].
+///
+/// Cloning the structure clones each container, meaning the clones refer to the same entities.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Default, Clone)]
+pub struct SceneEntities {
+    /// Entities implementing [`PreUpdate`] trait.
+    pub pre_update_entities: Entities<dyn PreUpdate>,
+    /// Entities implementing [`Update`] trait.
+    pub update_entities: Entities<dyn Update>,
+    /// Entities implementing [`LateUpdate`] trait.
+    pub late_update_entities: Entities<dyn LateUpdate>,
+    /// Entities implementing [`FixedUpdate`] trait.
+    pub fixed_update_entities: Entities<dyn FixedUpdate>,
+    /// Entities implementing [`Renderable`] trait.
+    pub renderable_entities: Entities<dyn Renderable>,
+    /// Entities implementing [`Interactive`] trait.
+    pub interactive_entities: Entities<dyn Interactive>,
+}
+
+/// A type representing a scene, such as a title screen, a level or a pause menu.
+///
+/// Scenes are managed as a stack by [`Game`](crate::game::Game). The top scene is always updated
+/// and rendered. Whether the scenes below it are updated or rendered as well is decided by
+/// [`Scene::update_below`] and [`Scene::render_below`].
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::scene::{Scene, SceneEntities};
+///
+/// #[derive(Default)]
+/// struct PauseMenu {
+///     entities: SceneEntities,
+/// }
+///
+/// impl Scene for PauseMenu {
+///     fn entities(&self) -> &SceneEntities {
+///         &self.entities
+///     }
+///
+///     // Keep drawing the gameplay scene underneath the menu, but freeze it.
+///     fn render_below(&self) -> bool {
+///         true
+///     }
+/// }
+/// ```
+pub trait Scene: Send {
+    /// Entity containers owned by the scene.
+    fn entities(&self) -> &SceneEntities;
+
+    /// Called when the scene is added to the scene stack.
+    fn on_enter(&mut self) {}
+
+    /// Called when the scene is removed from the scene stack.
+    fn on_exit(&mut self) {}
+
+    /// Whether the scene below this one should keep receiving events and all kinds of updates
+    /// while this scene is active.
+    fn update_below(&self) -> bool {
+        false
+    }
+
+    /// Whether the scene below this one should keep being rendered while this scene is active.
+    fn render_below(&self) -> bool {
+        false
+    }
+}
+
+impl SceneEntities {
+    // Remove every reference to the entity with the given address from all containers.
+    pub(crate) fn remove_address(&self, address: *const ()) {
+        self.pre_update_entities.remove_address(address);
+        self.update_entities.remove_address(address);
+        self.late_update_entities.remove_address(address);
+        self.fixed_update_entities.remove_address(address);
+        self.renderable_entities.remove_address(address);
+        self.interactive_entities.remove_address(address);
+    }
+}
+
+impl Scene for SceneEntities {
+    fn entities(&self) -> &SceneEntities {
+        self
+    }
+}
+
+// A pending change to the scene stack.
+enum SceneRequest {
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+}
+
+/// Handle to request changes to the scene stack of a [`Game`](crate::game::Game).
+///
+/// The handle can be cloned and stored in entities. Requests are not applied immediately; they
+/// are applied in order by the game at the end of the current frame, after all updates have run.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{
+///     error::CtraitResult,
+///     game::Game,
+///     scene::{SceneControl, SceneEntities},
+///     traits::Update,
+/// };
+///
+/// struct TitleScreen {
+///     scenes: SceneControl,
+///     elapsed: f32,
+/// }
+///
+/// impl Update for TitleScreen {
+///     fn update(&mut self, delta: f32) -> CtraitResult<()> {
+///         self.elapsed += delta;
+///         if self.elapsed > 3.0 {
+///             // Leave the title screen for the gameplay scene.
+///             self.scenes.replace(SceneEntities::default());
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let game = Game::new();
+/// let title_screen = TitleScreen {
+///     scenes: game.scene_control(),
+///     elapsed: 0.0,
+/// };
+/// ```
+#[allow(clippy::module_name_repetitions)]
+#[derive(Default, Clone)]
+pub struct SceneControl(Arc<Mutex<Vec<SceneRequest>>>);
+
+impl SceneControl {
+    /// Request the given scene to be pushed on top of the scene stack.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn push<S: Scene + 'static>(&self, scene: S) {
+        self.request(SceneRequest::Push(Box::new(scene)));
+    }
+
+    /// Request the top scene to be popped from the scene stack.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn pop(&self) {
+        self.request(SceneRequest::Pop);
+    }
+
+    /// Request the top scene to be replaced with the given scene.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn replace<S: Scene + 'static>(&self, scene: S) {
+        self.request(SceneRequest::Replace(Box::new(scene)));
+    }
+
+    fn request(&self, request: SceneRequest) {
+        self.0.lock().unwrap().push(request);
+    }
+}
+
+// Stack of scenes, with the top scene at the end.
+// It is shared with the fixed update thread.
+#[derive(Default, Clone)]
+pub(crate) struct SceneStack(Arc<Mutex<Vec<Box<dyn Scene>>>>);
+
+// Determines which of the stacked scenes are active.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ScenePhase {
+    Update,
+    Render,
+}
+
+impl SceneStack {
+    pub(crate) fn push(&self, mut scene: Box<dyn Scene>) {
+        scene.on_enter();
+        self.0.lock().unwrap().push(scene);
+    }
+
+    pub(crate) fn pop(&self) -> Option<Box<dyn Scene>> {
+        let mut scene = self.0.lock().unwrap().pop();
+        if let Some(scene) = &mut scene {
+            scene.on_exit();
+        }
+        scene
+    }
+
+    // Entity containers of every scene, from bottom to top.
+    pub(crate) fn entities(&self) -> Vec<SceneEntities> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|scene| SceneEntities::clone(scene.entities()))
+            .collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    // Apply and clear all pending requests of the given handle.
+    pub(crate) fn apply(&self, control: &SceneControl) {
+        let requests: Vec<SceneRequest> = control.0.lock().unwrap().drain(..).collect();
+        for request in requests {
+            match request {
+                SceneRequest::Push(scene) => self.push(scene),
+                SceneRequest::Pop => {
+                    self.pop();
+                }
+                SceneRequest::Replace(scene) => {
+                    self.pop();
+                    self.push(scene);
+                }
+            }
+        }
+    }
+
+    // Collect the selected container of every active scene, from bottom to top.
+    pub(crate) fn active<T: ?Sized>(
+        &self,
+        phase: ScenePhase,
+        select: fn(&SceneEntities) -> &Entities<T>,
+    ) -> Vec<Entities<T>> {
+        let scenes = self.0.lock().unwrap();
+        let mut active = Vec::new();
+        for scene in scenes.iter().rev() {
+            active.push(Entities::clone(select(scene.entities())));
+            let below = match phase {
+                ScenePhase::Update => scene.update_below(),
+                ScenePhase::Render => scene.render_below(),
+            };
+            if !below {
+                break;
+            }
+        }
+        active.reverse();
+        active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scene, SceneControl, SceneEntities, ScenePhase, SceneStack};
+
+    #[derive(Default)]
+    struct Overlay {
+        entities: SceneEntities,
+    }
+
+    impl Scene for Overlay {
+        fn entities(&self) -> &SceneEntities {
+            &self.entities
+        }
+
+        fn render_below(&self) -> bool {
+            true
+        }
+    }
+
+    fn update_count(stack: &SceneStack) -> usize {
+        stack
+            .active(ScenePhase::Update, |entities| &entities.update_entities)
+            .len()
+    }
+
+    fn render_count(stack: &SceneStack) -> usize {
+        stack
+            .active(ScenePhase::Render, |entities| &entities.renderable_entities)
+            .len()
+    }
+
+    #[test]
+    fn scene_stack_active() {
+        let stack = SceneStack::default();
+        assert_eq!(update_count(&stack), 0);
+        stack.push(Box::new(SceneEntities::default()));
+        stack.push(Box::new(Overlay::default()));
+        // Only the overlay is updated, but both scenes are rendered.
+        assert_eq!(update_count(&stack), 1);
+        assert_eq!(render_count(&stack), 2);
+        stack.push(Box::new(SceneEntities::default()));
+        assert_eq!(render_count(&stack), 1);
+    }
+
+    #[test]
+    fn scene_stack_apply() {
+        let stack = SceneStack::default();
+        let control = SceneControl::default();
+        control.push(SceneEntities::default());
+        control.push(Overlay::default());
+        // Requests are deferred until applied.
+        assert_eq!(stack.len(), 0);
+        stack.apply(&control);
+        assert_eq!(stack.len(), 2);
+        control.replace(SceneEntities::default());
+        stack.apply(&control);
+        assert_eq!(stack.len(), 2);
+        assert_eq!(render_count(&stack), 1);
+        control.pop();
+        control.pop();
+        control.pop();
+        stack.apply(&control);
+        assert_eq!(stack.len(), 0);
+        assert!(control.0.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+//! Timers and delayed callbacks driven by the game's clock.
+
+use crate::game::Game;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+type Callback = Box<dyn FnMut(&mut Game) + Send>;
+
+/// Handle to a scheduled timer, which can be used to cancel it.
+#[derive(Debug, Clone)]
+pub struct TimerHandle(Arc<AtomicBool>);
+
+impl TimerHandle {
+    /// Cancel the timer. Its callback will not be called anymore.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the timer has been cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// A timer waiting to be fired.
+pub(crate) struct ScheduledTimer {
+    remaining: f32,
+    interval: Option<f32>,
+    unscaled: bool,
+    cancelled: TimerHandle,
+    callback: Callback,
+}
+
+/// Handle to schedule callbacks on a [`Game`].
+///
+/// Timers are driven by the game's clock: they follow the game's time scale and do not advance
+/// while the game is paused, unless they are scheduled with unscaled time. Callbacks are called
+/// once per frame after the fixed updates (see the [phase order](crate::game::Game#phase-order)),
+/// and receive the game so they can modify it.
+///
+/// The handle can be cloned and stored in entities.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::game::Game;
+/// use std::sync::{
+///     atomic::{AtomicU32, Ordering},
+///     Arc,
+/// };
+///
+/// let mut game = Game::new();
+/// let scheduler = game.scheduler();
+///
+/// // Quit the game after ten seconds.
+/// scheduler.after(10.0, |game| game.control().quit());
+///
+/// // Count every second.
+/// let seconds = Arc::new(AtomicU32::new(0));
+/// let counter = Arc::clone(&seconds);
+/// let handle = scheduler.every(1.0, move |_| {
+///     counter.fetch_add(1, Ordering::SeqCst);
+/// });
+///
+/// for _ in 0..5 {
+///     game.step(1.0);
+/// }
+/// assert_eq!(seconds.load(Ordering::SeqCst), 5);
+/// handle.cancel();
+/// game.step(1.0);
+/// assert_eq!(seconds.load(Ordering::SeqCst), 5);
+/// ```
+#[derive(Default, Clone)]
+pub struct Scheduler(Arc<Mutex<Vec<ScheduledTimer>>>);
+
+impl Scheduler {
+    /// Call the given callback once, after the given number of seconds.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn after<F: FnMut(&mut Game) + Send + 'static>(&self, seconds: f32, f: F) -> TimerHandle {
+        self.schedule(seconds, None, false, Box::new(f))
+    }
+
+    /// Call the given callback repeatedly, every given number of seconds.
+    ///
+    /// If more than one interval passes within a single frame, the callback is called once per
+    /// interval. An interval of zero calls the callback once every frame.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn every<F: FnMut(&mut Game) + Send + 'static>(&self, seconds: f32, f: F) -> TimerHandle {
+        self.schedule(seconds, Some(seconds), false, Box::new(f))
+    }
+
+    /// Like [`Self::after`], but the timer ignores the game's time scale and pause state.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn after_unscaled<F: FnMut(&mut Game) + Send + 'static>(
+        &self,
+        seconds: f32,
+        f: F,
+    ) -> TimerHandle {
+        self.schedule(seconds, None, true, Box::new(f))
+    }
+
+    /// Like [`Self::every`], but the timer ignores the game's time scale and pause state.
+    ///
+    /// # Panics
+    ///
+    /// This function might panic if another user of the handle panics.
+    pub fn every_unscaled<F: FnMut(&mut Game) + Send + 'static>(
+        &self,
+        seconds: f32,
+        f: F,
+    ) -> TimerHandle {
+        self.schedule(seconds, Some(seconds), true, Box::new(f))
+    }
+
+    fn schedule(
+        &self,
+        seconds: f32,
+        interval: Option<f32>,
+        unscaled: bool,
+        callback: Callback,
+    ) -> TimerHandle {
+        let handle = TimerHandle(Arc::new(AtomicBool::new(false)));
+        self.insert(ScheduledTimer {
+            remaining: seconds,
+            interval,
+            unscaled,
+            cancelled: TimerHandle::clone(&handle),
+            callback,
+        });
+        handle
+    }
+
+    fn insert(&self, timer: ScheduledTimer) {
+        self.0.lock().unwrap().push(timer);
+    }
+
+    // Advance all timers, removing and returning the ones which are due.
+    // Timers are skipped if their delta is None.
+    fn advance(&self, scaled: Option<f32>, unscaled: Option<f32>) -> Vec<ScheduledTimer> {
+        let mut timers = self.0.lock().unwrap();
+        timers.retain(|timer| !timer.cancelled.is_cancelled());
+        let mut due = Vec::new();
+        let mut index = 0;
+        while index < timers.len() {
+            let timer = &mut timers[index];
+            let Some(delta) = (if timer.unscaled { unscaled } else { scaled }) else {
+                // The timer's clock did not advance, so it is not due, even with a zero delay.
+                index += 1;
+                continue;
+            };
+            timer.remaining -= delta;
+            if timer.remaining <= 0.0 {
+                due.push(timers.remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        due
+    }
+
+    // Advance all timers and call the callbacks of the ones which are due.
+    pub(crate) fn run(&self, game: &mut Game, scaled: Option<f32>, unscaled: Option<f32>) {
+        // The lock is released before callbacks are called, so they can schedule timers.
+        for mut timer in self.advance(scaled, unscaled) {
+            while !timer.cancelled.is_cancelled() {
+                (timer.callback)(game);
+                match timer.interval {
+                    Some(interval) if interval > 0.0 => {
+                        timer.remaining += interval;
+                        if timer.remaining > 0.0 {
+                            self.insert(timer);
+                            break;
+                        }
+                    }
+                    Some(_) => {
+                        timer.remaining = 0.0;
+                        self.insert(timer);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Game, Scheduler};
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    fn counter() -> (Arc<AtomicU32>, impl FnMut(&mut Game) + Send + 'static) {
+        let count = Arc::new(AtomicU32::new(0));
+        let clone = Arc::clone(&count);
+        (count, move |_: &mut Game| {
+            clone.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    #[test]
+    fn scheduler_after() {
+        let scheduler = Scheduler::default();
+        let mut game = Game::default();
+        let (count, callback) = counter();
+        scheduler.after(1.0, callback);
+        scheduler.run(&mut game, Some(0.5), Some(0.5));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        scheduler.run(&mut game, Some(0.5), Some(0.5));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        scheduler.run(&mut game, Some(5.0), Some(5.0));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(scheduler.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn scheduler_every() {
+        let scheduler = Scheduler::default();
+        let mut game = Game::default();
+        let (count, callback) = counter();
+        let handle = scheduler.every(1.0, callback);
+        scheduler.run(&mut game, Some(3.5), Some(3.5));
+        // Called once per elapsed interval.
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        handle.cancel();
+        scheduler.run(&mut game, Some(3.5), Some(3.5));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        assert!(scheduler.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn scheduler_unscaled() {
+        let scheduler = Scheduler::default();
+        let mut game = Game::default();
+        let (scaled_count, scaled) = counter();
+        let (unscaled_count, unscaled) = counter();
+        scheduler.every(1.0, scaled);
+        scheduler.every_unscaled(1.0, unscaled);
+        // Paused: scaled timers do not advance.
+        scheduler.run(&mut game, None, Some(1.0));
+        assert_eq!(scaled_count.load(Ordering::SeqCst), 0);
+        assert_eq!(unscaled_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn scheduler_paused_zero_delay() {
+        let scheduler = Scheduler::default();
+        let mut game = Game::default();
+        let (after_count, after) = counter();
+        let (every_count, every) = counter();
+        scheduler.after(0.0, after);
+        scheduler.every(0.0, every);
+        // Paused: zero-delay timers are not due until the scaled clock advances.
+        scheduler.run(&mut game, None, Some(1.0));
+        scheduler.run(&mut game, None, Some(1.0));
+        assert_eq!(after_count.load(Ordering::SeqCst), 0);
+        assert_eq!(every_count.load(Ordering::SeqCst), 0);
+        scheduler.run(&mut game, Some(0.0), Some(0.0));
+        assert_eq!(after_count.load(Ordering::SeqCst), 1);
+        assert_eq!(every_count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,6 +1,8 @@
 //! Sprite used to display textures.
 
-use crate::{camera::Camera, graphics::RenderContext, rect::Rect, traits::Renderable};
+use crate::{
+    camera::Camera, error::CtraitResult, graphics::RenderContext, rect::Rect, traits::Renderable,
+};
 use std::path::PathBuf;
 
 /// A sprite which holds a path to a texture and a [`Rect`].
@@ -38,14 +40,14 @@ impl Sprite {
 }
 
 impl Renderable for Sprite {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
         if let Some(canvas_rect) = self.rect.as_canvas_rect(camera) {
             let texture = context
                 .texture_manager
-                .load(&self.path.as_os_str().to_string_lossy())
-                .unwrap();
-            context.canvas.copy(&texture, None, canvas_rect).unwrap();
+                .load(&self.path.as_os_str().to_string_lossy())?;
+            context.canvas.copy(&texture, None, canvas_rect)?;
         }
+        Ok(())
     }
 }
 
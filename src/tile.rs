@@ -198,7 +198,7 @@ impl<const ROWS: usize, const COLUMNS: usize> Tilemap<ROWS, COLUMNS> {
 }
 
 impl<const ROWS: usize, const COLUMNS: usize> Renderable for Tilemap<ROWS, COLUMNS> {
-    fn render(&self, camera: &Camera, context: &mut RenderContext) {
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
         let half_tilemap_dimensions = (Vector2::new(COLUMNS, ROWS).cast() * self.tile_size) / 2.;
         for row in 0..ROWS {
             for column in 0..COLUMNS {
@@ -217,12 +217,12 @@ impl<const ROWS: usize, const COLUMNS: usize> Renderable for Tilemap<ROWS, COLUM
                             Tile::Sprite(path) => {
                                 // Render sprite.
                                 let sprite = Sprite::new(path, rect);
-                                sprite.render(camera, context);
+                                sprite.render(camera, context)?;
                             }
                             Tile::Color(color) => {
                                 // Render rect with specified color.
                                 let rect = rect.with_color(*color);
-                                rect.render(camera, context);
+                                rect.render(camera, context)?;
                             }
                         }
                     } else {
@@ -231,6 +231,7 @@ impl<const ROWS: usize, const COLUMNS: usize> Renderable for Tilemap<ROWS, COLUM
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -1,27 +1,221 @@
 //! Traits that structs can implement.
 
-use crate::{camera::Camera, graphics::RenderContext};
+use crate::{
+    camera::Camera,
+    entity::Entity,
+    error::CtraitResult,
+    game::Game,
+    graphics::{Layers, RenderContext},
+    transform::Transform,
+};
 use sdl2::event::Event;
 
+/// Name of a type, used in diagnostics such as panic reports and
+/// [`FrameStats::render_times`](crate::game::FrameStats::render_times).
+///
+/// This trait is implemented for every sized type and is a supertrait of every entity trait, so
+/// the name is the one of the entity's concrete type even behind a trait object.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{error::CtraitResult, traits::{TypeName, Update}};
+///
+/// struct Player;
+///
+/// impl Update for Player {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let player: Box<dyn Update> = Box::new(Player);
+/// assert!(TypeName::type_name(&*player).ends_with("Player"));
+/// ```
+pub trait TypeName {
+    /// Returns the name of the type, as given by [`std::any::type_name`].
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T> TypeName for T {
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+}
+
 /// A type that should update every game loop iteration.
 ///
+/// Like every method called by the game on its entities, [`Update::update`] is fallible. Errors
+/// are reported to the game's error handler (see
+/// [`Game::with_error_handler`](crate::game::Game::with_error_handler)).
+///
 /// # Examples
 ///
 /// ```
-/// use ctrait::traits::Update;
+/// use ctrait::{error::CtraitResult, traits::Update};
 ///
 /// struct UpdateExample;
 ///
 /// impl Update for UpdateExample {
-///     fn update(&mut self, delta: f32) {
+///     fn update(&mut self, delta: f32) -> CtraitResult<()> {
 ///         // Any code here will be executed every frame.
+///         Ok(())
 ///     }
 /// }
 /// ```
-pub trait Update: Send {
+pub trait Update: TypeName + Send + Sync {
     /// Called once per game loop iteration.
     /// `delta` is the number of seconds since the last update.
-    fn update(&mut self, delta: f32);
+    fn update(&mut self, delta: f32) -> CtraitResult<()>;
+
+    /// Whether the entity should receive unscaled time.
+    ///
+    /// If `true`, `delta` is not affected by the game's time scale and the entity keeps being
+    /// updated while the game is paused. This is useful for pause menus and user interfaces.
+    /// Defaults to `false`.
+    fn unscaled_time(&self) -> bool {
+        false
+    }
+
+    /// Whether the entity must be updated serially on the thread running the game loop.
+    ///
+    /// This only matters when parallel updates are enabled (see
+    /// `Game::with_parallel_updates`, which requires the `parallel` feature). Entities relying on
+    /// the order of updates or on thread-local state should return `true`. Defaults to `false`.
+    fn main_thread(&self) -> bool {
+        false
+    }
+
+    /// Whether the entity is active, meaning [`Update::update`] is called.
+    ///
+    /// Inactive entities are skipped without being removed from their containers. Each trait has
+    /// its own flag, named after the trait's method, so an entity can for example keep being
+    /// rendered while its updates are frozen. Defaults to `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{
+    ///     camera::Camera,
+    ///     error::CtraitResult,
+    ///     graphics::RenderContext,
+    ///     traits::{Renderable, Update},
+    /// };
+    ///
+    /// struct Enemy {
+    ///     frozen: bool,
+    ///     hidden: bool,
+    /// }
+    ///
+    /// impl Update for Enemy {
+    ///     fn update(&mut self, delta: f32) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn update_active(&self) -> bool {
+    ///         !self.frozen
+    ///     }
+    /// }
+    ///
+    /// impl Renderable for Enemy {
+    ///     fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn render_active(&self) -> bool {
+    ///         !self.hidden
+    ///     }
+    /// }
+    ///
+    /// let enemy = Enemy {
+    ///     frozen: true,
+    ///     hidden: false,
+    /// };
+    /// assert!(!enemy.update_active());
+    /// assert!(enemy.render_active());
+    /// ```
+    fn update_active(&self) -> bool {
+        true
+    }
+}
+
+/// A type that should update every game loop iteration, before any other update.
+///
+/// This is useful to sample input state before it is used by other entities.
+/// See the [phase order](crate::game::Game#phase-order) for details.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{error::CtraitResult, traits::PreUpdate};
+///
+/// struct PreUpdateExample;
+///
+/// impl PreUpdate for PreUpdateExample {
+///     fn pre_update(&mut self, delta: f32) -> CtraitResult<()> {
+///         // Any code here will be executed every frame, before Update and FixedUpdate.
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait PreUpdate: TypeName + Send + Sync {
+    /// Called once per game loop iteration, before [`FixedUpdate::fixed_update`] and
+    /// [`Update::update`].
+    /// `delta` is the number of seconds since the last update.
+    fn pre_update(&mut self, delta: f32) -> CtraitResult<()>;
+
+    /// Whether the entity should receive unscaled time.
+    ///
+    /// See [`Update::unscaled_time`].
+    fn unscaled_time(&self) -> bool {
+        false
+    }
+
+    /// Whether the entity is active, meaning [`PreUpdate::pre_update`] is called.
+    ///
+    /// See [`Update::update_active`].
+    fn pre_update_active(&self) -> bool {
+        true
+    }
+}
+
+/// A type that should update every game loop iteration, after every [`Update`].
+///
+/// This is useful for logic depending on the result of other updates, such as a camera
+/// following the player. See the [phase order](crate::game::Game#phase-order) for details.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{error::CtraitResult, traits::LateUpdate};
+///
+/// struct LateUpdateExample;
+///
+/// impl LateUpdate for LateUpdateExample {
+///     fn late_update(&mut self, delta: f32) -> CtraitResult<()> {
+///         // Any code here will be executed every frame, after Update.
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait LateUpdate: TypeName + Send + Sync {
+    /// Called once per game loop iteration, after [`Update::update`].
+    /// `delta` is the number of seconds since the last update.
+    fn late_update(&mut self, delta: f32) -> CtraitResult<()>;
+
+    /// Whether the entity should receive unscaled time.
+    ///
+    /// See [`Update::unscaled_time`].
+    fn unscaled_time(&self) -> bool {
+        false
+    }
+
+    /// Whether the entity is active, meaning [`LateUpdate::late_update`] is called.
+    ///
+    /// See [`Update::update_active`].
+    fn late_update_active(&self) -> bool {
+        true
+    }
 }
 
 /// A type that should update every fixed timestep.
@@ -31,21 +225,45 @@ pub trait Update: Send {
 /// # Examples
 ///
 /// ```
-/// use ctrait::traits::FixedUpdate;
+/// use ctrait::{error::CtraitResult, traits::FixedUpdate};
 ///
 /// struct FixedUpdateExample;
 ///
 /// impl FixedUpdate for FixedUpdateExample {
-///     fn fixed_update(&mut self, delta: f32) {
+///     fn fixed_update(&mut self, delta: f32) -> CtraitResult<()> {
 ///         // Any code here will be executed at a fixed rate.
+///         Ok(())
 ///     }
 /// }
 /// ```
-pub trait FixedUpdate: Send {
+pub trait FixedUpdate: TypeName + Send + Sync {
     /// `delta` is the number of seconds since the last update.
-    /// It should be approximately equal to the default timestep
+    /// With [`FixedUpdateMode::Accumulated`](crate::game::FixedUpdateMode::Accumulated), it is
+    /// exactly equal to the game's timestep, which defaults to
     /// [`Game::DEFAULT_TIMESTEP`](crate::game::Game::DEFAULT_TIMESTEP).
-    fn fixed_update(&mut self, delta: f32);
+    fn fixed_update(&mut self, delta: f32) -> CtraitResult<()>;
+
+    /// Whether the entity should be stepped according to unscaled time.
+    ///
+    /// If `true`, the entity is not affected by the game's time scale and keeps being stepped
+    /// while the game is paused. Defaults to `false`.
+    fn unscaled_time(&self) -> bool {
+        false
+    }
+
+    /// Whether the entity must be stepped serially on the thread running the fixed steps.
+    ///
+    /// See [`Update::main_thread`].
+    fn main_thread(&self) -> bool {
+        false
+    }
+
+    /// Whether the entity is active, meaning [`FixedUpdate::fixed_update`] is called.
+    ///
+    /// See [`Update::update_active`].
+    fn fixed_update_active(&self) -> bool {
+        true
+    }
 }
 
 /// A type that is responsive to user events.
@@ -53,12 +271,12 @@ pub trait FixedUpdate: Send {
 /// # Examples
 ///
 /// ```
-/// use ctrait::{Event, traits::Interactive};
+/// use ctrait::{error::CtraitResult, traits::Interactive, Event};
 ///
 /// struct InteractiveExample;
 ///
 /// impl Interactive for InteractiveExample {
-///     fn on_event(&mut self, event: &Event) {
+///     fn on_event(&mut self, event: &Event) -> CtraitResult<()> {
 ///         match event {
 ///             Event::KeyDown {
 ///                 keycode: Some(keycode),
@@ -74,30 +292,350 @@ pub trait FixedUpdate: Send {
 ///             }
 ///             _ => {}
 ///         }
+///         Ok(())
 ///     }
 /// }
 /// ```
-pub trait Interactive: Send {
+pub trait Interactive: TypeName + Send + Sync {
     /// Called for each event in the event queue.
-    fn on_event(&mut self, event: &Event);
+    fn on_event(&mut self, event: &Event) -> CtraitResult<()>;
+
+    /// Whether the entity is active, meaning [`Interactive::on_event`] is called.
+    ///
+    /// See [`Update::update_active`].
+    fn event_active(&self) -> bool {
+        true
+    }
 }
 
 /// A type that can be rendered.
 ///
+/// Entities are rendered layer by layer, and sorted by [`z_index`](Self::z_index) within a layer.
+///
 /// # Examples
 ///
 /// ```
-/// use ctrait::{camera::Camera, graphics::RenderContext, traits::Renderable};
+/// use ctrait::{camera::Camera, error::CtraitResult, graphics::RenderContext, traits::Renderable};
 ///
 /// struct RenderableExample;
 ///
 /// impl Renderable for RenderableExample {
-///     fn render(&self, camera: &Camera, context: &mut RenderContext) {
+///     fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
 ///         // Render logic goes here.
+///         Ok(())
 ///     }
 /// }
 /// ```
-pub trait Renderable: Send {
+pub trait Renderable: TypeName + Send + Sync {
     /// Called by [`Renderer`](crate::graphics::Renderer).
-    fn render(&self, camera: &Camera, context: &mut RenderContext);
+    ///
+    /// The entity is only locked for reading while rendered, so rendering does not wait for other
+    /// readers of the entity.
+    fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()>;
+
+    /// Name of the layer the entity is rendered in, see [`Layers`].
+    /// Defaults to [`Layers::DEFAULT`].
+    fn layer(&self) -> &str {
+        Layers::DEFAULT
+    }
+
+    /// Order of the entity within its layer. Entities with a greater z-index are rendered on top.
+    ///
+    /// Entities with the same layer and z-index are rendered in the order they were added.
+    /// Defaults to `0`.
+    fn z_index(&self) -> i32 {
+        0
+    }
+
+    /// Whether the entity is active, meaning [`Renderable::render`] is called.
+    ///
+    /// See [`Update::update_active`].
+    fn render_active(&self) -> bool {
+        true
+    }
+}
+
+/// A type that should be notified once before its first update.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{error::CtraitResult, graphics::RenderContext, traits::Start};
+///
+/// struct StartExample;
+///
+/// impl Start for StartExample {
+///     fn start(&mut self, context: &mut RenderContext) -> CtraitResult<()> {
+///         // Load resources, such as textures, here.
+///         context.texture_manager.load("path/to/image.png")?;
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Start: TypeName + Send + Sync {
+    /// Called by [`Game::start`](crate::game::Game::start) once, at the beginning of the first
+    /// frame after the entity was registered and before any updates of that frame.
+    fn start(&mut self, context: &mut RenderContext) -> CtraitResult<()>;
+}
+
+/// A type that should be notified before it is removed from the game.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{error::CtraitResult, traits::Destroy};
+///
+/// struct DestroyExample;
+///
+/// impl Destroy for DestroyExample {
+///     fn destroy(&mut self) -> CtraitResult<()> {
+///         // Clean up here.
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Destroy: TypeName + Send + Sync {
+    /// Called once the entity is no longer referred to outside of the game, right before it is
+    /// dropped.
+    fn destroy(&mut self) -> CtraitResult<()>;
+}
+
+/// A type with a transform, which can be part of a [`Hierarchy`](crate::hierarchy::Hierarchy).
+///
+/// [`Renderable`] entities can render relative to their world transform by storing it when
+/// notified, for example with [`Rect::transformed`](crate::rect::Rect::transformed).
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{
+///     camera::Camera,
+///     error::CtraitResult,
+///     graphics::RenderContext,
+///     rect::Rect,
+///     traits::{Renderable, Spatial},
+///     transform::Transform,
+/// };
+///
+/// struct Weapon {
+///     rect: Rect,
+///     local: Transform,
+///     world: Transform,
+/// }
+///
+/// impl Spatial for Weapon {
+///     fn local_transform(&self) -> Transform {
+///         self.local
+///     }
+///
+///     fn set_world_transform(&mut self, transform: Transform) {
+///         self.world = transform;
+///     }
+/// }
+///
+/// impl Renderable for Weapon {
+///     fn render(&self, camera: &Camera, context: &mut RenderContext) -> CtraitResult<()> {
+///         // The rectangle is relative to the weapon, which is relative to its parent.
+///         self.rect.transformed(&self.world).render(camera, context)
+///     }
+/// }
+/// ```
+pub trait Spatial: TypeName + Send + Sync {
+    /// Transform of the entity relative to its parent, or to the world if it has no parent.
+    fn local_transform(&self) -> Transform;
+
+    /// Called with the world transform of the entity every time transforms are propagated.
+    fn set_world_transform(&mut self, transform: Transform);
+}
+
+/// A type that can register itself in every entity container of a [`Game`] whose trait it
+/// implements.
+///
+/// This is used by [`Game::spawn`]. Rather than implementing it by hand, it is recommended to
+/// use the [`spawnable`](crate::spawnable) macro.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{
+///     entity,
+///     entity::Entity,
+///     error::CtraitResult,
+///     game::Game,
+///     traits::{Spawn, Update},
+/// };
+///
+/// struct SpawnExample;
+///
+/// impl Update for SpawnExample {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// impl Spawn for SpawnExample {
+///     fn spawn(entity: &Entity<Self>, game: &mut Game) {
+///         game.register(&(Entity::clone(entity) as Entity<dyn Update>));
+///     }
+/// }
+///
+/// let mut game = Game::new();
+/// let example = entity!(SpawnExample);
+/// game.spawn(&example);
+/// assert!(game.update_entities.contains(&example));
+/// ```
+pub trait Spawn: Send + Sync + Sized + 'static {
+    /// Register the entity in the containers of the game, with [`Game::register`].
+    fn spawn(entity: &Entity<Self>, game: &mut Game);
+}
+
+/// Macro to implement [`Spawn`](crate::traits::Spawn) for a type.
+///
+/// The entity is registered in every entity container of the game whose trait its type
+/// implements. The type can be followed by a list of these traits, which fails to compile unless
+/// the type implements every one of them, documenting which containers it is expected in.
+///
+/// The implemented traits are found where the macro is invoked, so the type must be concrete: a
+/// generic type cannot be made spawnable with this macro, and [`Spawn`](crate::traits::Spawn)
+/// has to be implemented by hand instead.
+///
+/// # Examples
+///
+/// ```
+/// use ctrait::{
+///     camera::Camera,
+///     entity,
+///     error::CtraitResult,
+///     game::Game,
+///     graphics::RenderContext,
+///     spawnable,
+///     traits::{Renderable, Update},
+/// };
+///
+/// struct Ball;
+///
+/// impl Update for Ball {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// impl Renderable for Ball {
+///     fn render(&self, _: &Camera, _: &mut RenderContext) -> CtraitResult<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// spawnable!(Ball: Update, Renderable);
+///
+/// let mut game = Game::new();
+/// let ball = entity!(Ball);
+/// game.spawn(&ball);
+/// assert!(game.update_entities.contains(&ball));
+/// assert!(game.renderable_entities.contains(&ball));
+/// ```
+///
+/// Without a list, the entity is still registered in every container of its traits.
+///
+/// ```
+/// use ctrait::{entity, error::CtraitResult, game::Game, spawnable, traits::Update};
+///
+/// struct Ball;
+///
+/// impl Update for Ball {
+///     fn update(&mut self, _: f32) -> CtraitResult<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// spawnable!(Ball);
+///
+/// let mut game = Game::new();
+/// let ball = entity!(Ball);
+/// game.spawn(&ball);
+/// assert!(game.update_entities.contains(&ball));
+/// ```
+///
+/// Listing a trait the type does not implement fails to compile.
+///
+/// ```compile_fail
+/// use ctrait::{spawnable, traits::Update};
+///
+/// struct Ball;
+///
+/// spawnable!(Ball: Update);
+/// ```
+#[macro_export]
+macro_rules! spawnable {
+    ($type:ty $(: $($name:ident),+ $(,)?)?) => {
+        impl $crate::traits::Spawn for $type {
+            fn spawn(entity: &$crate::entity::Entity<Self>, game: &mut $crate::game::Game) {
+                #[allow(unused_imports)]
+                use $crate::traits::spawn::*;
+                $($(
+                    let _: &$crate::entity::Entity<dyn $name> =
+                        &($crate::entity::Entity::clone(entity) as _);
+                )+)?
+                let probe = $crate::traits::spawn::Probe(entity);
+                (&probe).register_pre_update(game);
+                (&probe).register_update(game);
+                (&probe).register_late_update(game);
+                (&probe).register_fixed_update(game);
+                (&probe).register_renderable(game);
+                (&probe).register_interactive(game);
+                (&probe).register_start(game);
+                (&probe).register_destroy(game);
+            }
+        }
+    };
+}
+
+// Registration of an entity in the container of every trait its type implements, used by the
+// `spawnable` macro. Each trait has a pair of extension traits with the same method: the one
+// implemented for `Probe` when the type implements the trait registers the entity, while the one
+// implemented for `&Probe` does nothing. Method resolution on `&Probe` prefers the former, and
+// only falls back to the latter when the type does not implement the trait.
+#[doc(hidden)]
+pub mod spawn {
+    use super::{
+        Destroy, FixedUpdate, Interactive, LateUpdate, PreUpdate, Renderable, Start, Update,
+    };
+    use crate::{entity::Entity, game::Game};
+
+    pub struct Probe<'a, T>(pub &'a Entity<T>);
+
+    macro_rules! probe {
+        ($($name:ident => $method:ident, $register:ident, $skip:ident);+ $(;)?) => {
+            $(
+                pub trait $register {
+                    fn $method(&self, game: &mut Game);
+                }
+
+                impl<T: $name + 'static> $register for Probe<'_, T> {
+                    fn $method(&self, game: &mut Game) {
+                        game.register(&(Entity::clone(self.0) as Entity<dyn $name>));
+                    }
+                }
+
+                pub trait $skip {
+                    fn $method(&self, game: &mut Game);
+                }
+
+                impl<T> $skip for &Probe<'_, T> {
+                    fn $method(&self, _: &mut Game) {}
+                }
+            )+
+        };
+    }
+
+    probe!(
+        PreUpdate => register_pre_update, RegisterPreUpdate, SkipPreUpdate;
+        Update => register_update, RegisterUpdate, SkipUpdate;
+        LateUpdate => register_late_update, RegisterLateUpdate, SkipLateUpdate;
+        FixedUpdate => register_fixed_update, RegisterFixedUpdate, SkipFixedUpdate;
+        Renderable => register_renderable, RegisterRenderable, SkipRenderable;
+        Interactive => register_interactive, RegisterInteractive, SkipInteractive;
+        Start => register_start, RegisterStart, SkipStart;
+        Destroy => register_destroy, RegisterDestroy, SkipDestroy;
+    );
 }
@@ -0,0 +1,110 @@
+//! Transform relative to a parent or to the world.
+
+use crate::math::Vector2;
+use std::ops::Mul;
+
+/// Position and scale, relative to a parent or to the world.
+///
+/// Transforms are combined with `*`: `parent * local` is the transform of a child relative to
+/// the parent's own reference, such as the world. See
+/// [`Hierarchy`](crate::hierarchy::Hierarchy).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Translation of the transform.
+    pub position: Vector2<f32>,
+    /// Scale of the transform along each axis.
+    pub scale: Vector2<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    /// Returns the transform which neither translates nor scales.
+    #[must_use]
+    pub fn identity() -> Self {
+        Self {
+            position: Vector2::zeros(),
+            scale: Vector2::repeat(1.0),
+        }
+    }
+
+    /// Constructs a new transform with the given position and no scaling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{math::Vector2, transform::Transform};
+    ///
+    /// let player = Transform::new(Vector2::new(10.0, 0.0));
+    /// // The weapon is held 2 units to the right of the player.
+    /// let weapon = Transform::new(Vector2::new(2.0, 0.0));
+    /// assert_eq!((player * weapon).position, Vector2::new(12.0, 0.0));
+    /// ```
+    #[must_use]
+    pub fn new(position: Vector2<f32>) -> Self {
+        Self {
+            position,
+            ..Self::identity()
+        }
+    }
+
+    /// Construct transform with the given scale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctrait::{math::Vector2, transform::Transform};
+    ///
+    /// let panel = Transform::new(Vector2::new(100.0, 100.0)).with_scale(Vector2::repeat(2.0));
+    /// let button = Transform::new(Vector2::new(5.0, 5.0));
+    /// assert_eq!((panel * button).position, Vector2::new(110.0, 110.0));
+    /// ```
+    #[must_use]
+    pub fn with_scale(mut self, scale: Vector2<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns the given point, relative to this transform, relative to the transform's own
+    /// reference instead.
+    #[must_use]
+    pub fn transform_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        self.position + self.scale.component_mul(&point)
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, local: Self) -> Self {
+        Self {
+            position: self.transform_point(local.position),
+            scale: self.scale.component_mul(&local.scale),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Transform, Vector2};
+
+    #[test]
+    fn transform_default() {
+        let transform = Transform::default();
+        assert_eq!(transform, Transform::identity());
+        assert_eq!(transform * transform, Transform::identity());
+    }
+
+    #[test]
+    fn transform_mul() {
+        let parent = Transform::new(Vector2::new(1.0, 2.0)).with_scale(Vector2::new(2.0, 3.0));
+        let local = Transform::new(Vector2::new(1.0, 1.0)).with_scale(Vector2::new(0.5, 2.0));
+        let world = parent * local;
+        assert_eq!(world.position, Vector2::new(3.0, 5.0));
+        assert_eq!(world.scale, Vector2::new(1.0, 6.0));
+    }
+}